@@ -0,0 +1,594 @@
+//! purpose:
+//!     commands.rs runs the satview subcommands: it loads satellites, builds observers and
+//!     the time range from the flags, then calls into the library and prints or saves the result.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use chrono::{NaiveDateTime, TimeDelta};
+use satview::lcp::plan::load_lcp;
+use satview::lcp::validate::{validate_plan, ObserverCheck, ValidationConfig, ValidationReport};
+use satview::link::doppler::{doppler_series, write_doppler_table};
+use satview::link::path_loss::CarrierFrequencies;
+use satview::obs::observer::Observer;
+use satview::obs::observer_locations::SiteRegistry;
+use satview::obs::site::{load_sites, Site, DEFAULT_SITE_COLOR};
+use satview::render::chart::{draw_time_series_chart, handover_markers, ChartConfig, ChartQuantity};
+use satview::render::footprint::draw_footprints;
+use satview::render::groundtrack::{draw_ground_track, ground_track, track_color};
+use satview::render::map::{color_pixel, get_pixel_vector, gimme_point, load_map};
+use satview::render::projection::{reproject_map, AzimuthalEquidistant, Equirectangular, Projection, WebMercator};
+use satview::render::skyplot::{draw_sky_plot, SkyPlotConfig};
+use satview::sat::constellation::{generate_constellation, ConstellationConfig, ConstellationPattern};
+use satview::sat::keplerian::KeplerianElements;
+use satview::sat::ephemeris::{Interpolation, DEFAULT_HERMITE_POINTS, DEFAULT_LAGRANGE_POINTS};
+use satview::sat::orbit_file::load_orbit_file;
+use satview::sat::numerical::NumericalPropagator;
+use satview::sat::propagator::Propagator;
+use satview::sat::satellite::Satellite;
+use satview::sat::staleness::{days, StalenessAction, StalenessPolicy};
+use satview::sat::tle::write_tles;
+use satview::sat::twobody::TwoBodyPropagator;
+use satview::vis::joint::find_joint_windows;
+use satview::vis::passes::{find_passes, PassConfig};
+use crate::cli::{ChartsArgs, Command, ConstellationArgs, DopplerArgs, InterpolationMethod, MapArgs, MapProjection,
+                 ObserverArgs, PassesArgs, PropagatorKind, Pattern, SatelliteArgs, SkyplotArgs, StaleAction, TimeArgs, ValidateLcpArgs};
+
+/// An observer with the name and colour it is reported and drawn with.
+struct Station {
+    name: String,
+    observer: Observer,
+    color: [u8; 4],
+}
+
+/// run: runs one subcommand.
+/// output: failure exit code when an LCP does not validate
+pub fn run(command: Command) -> anyhow::Result<ExitCode> {
+    match command {
+        Command::Passes(args) => run_passes(args),
+        Command::Map(args) => run_map(args),
+        Command::Skyplot(args) => run_skyplot(args),
+        Command::Doppler(args) => run_doppler(args),
+        Command::Charts(args) => run_charts(args),
+        Command::ValidateLcp(args) => run_validate_lcp(args),
+        Command::Constellation(args) => run_constellation(args),
+    }
+}
+
+fn run_passes(args: PassesArgs) -> anyhow::Result<ExitCode> {
+    let mut satellites = load_satellites(&args.satellites)?;
+    let stations = stations(&args.observers)?;
+    if args.joint && stations.len() < 2 {
+        anyhow::bail!("--joint needs at least two observers, {} given", stations.len());
+    }
+    let (start, end) = time_range(&args.time, &satellites)?;
+    let config = PassConfig {
+        step: args.time.step.unwrap_or(PassConfig::default().step),
+        ..Default::default()
+    };
+
+    for station in &stations {
+        println!("{}", describe_station(station));
+        for satellite in satellites.iter_mut() {
+            let name = satellite.name();
+            let pass_config = PassConfig { min_elevation: station.observer.elevation_mask, ..config };
+            for window in find_passes(&station.observer, satellite, &start, &end, &pass_config)? {
+                let tle_age = window.tle_age
+                    .map_or(String::new(), |(aos, los)| format!(" | TLE age {:+.2} to {:+.2} days", days(&aos), days(&los)));
+                println!("{} AOS {} az {:.1} | max el {:.1} at {} az {:.1} | LOS {} az {:.1} | {} s{}",
+                         name,
+                         window.aos, window.aos_azimuth,
+                         window.max_elevation, window.max_elevation_time, window.max_elevation_azimuth,
+                         window.los, window.los_azimuth,
+                         window.duration().num_seconds(),
+                         tle_age);
+            }
+        }
+    }
+
+    if args.joint {
+        let observers: Vec<Observer> = stations.iter().map(|station| station.observer).collect();
+        println!("joint windows, all {} observers", observers.len());
+        for satellite in satellites.iter_mut() {
+            let name = satellite.name();
+            for window in find_joint_windows(&observers, satellite, &start, &end, &config)? {
+                println!("{} {} to {} | {} s | opened by {}, closed by {}",
+                         name, window.start, window.end, window.duration().num_seconds(),
+                         stations[window.start_observer].name, stations[window.end_observer].name);
+            }
+        }
+    }
+    print_tle_ages(&satellites);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_map(args: MapArgs) -> anyhow::Result<ExitCode> {
+    // Read the map in parallel with loading and propagating the satellites.
+    let map_file = args.map.to_string_lossy().into_owned();
+    let map_handle = thread::spawn(move || load_map(&map_file));
+
+    let mut satellites = load_satellites(&args.satellites)?;
+    let stations = stations(&args.observers)?;
+    let (start, end) = time_range(&args.time, &satellites)?;
+    let step = args.time.step.unwrap_or(TimeDelta::seconds(30));
+    let mut tracks = Vec::new();
+    for satellite in satellites.iter_mut() {
+        tracks.push(ground_track(satellite, &start, &end, &step)?);
+    }
+
+    let (map, height, width) = map_handle.join()
+        .map_err(|_| anyhow::anyhow!("map loading thread panicked"))??;
+    let projection: Box<dyn Projection> = match args.projection {
+        MapProjection::Equirectangular => Box::new(Equirectangular { width, height }),
+        MapProjection::WebMercator => Box::new(WebMercator { width, height: width }),
+        MapProjection::Azimuthal => Box::new(AzimuthalEquidistant {
+            width: height,
+            height,
+            center: stations[0].observer.geodetic_coordinates,
+        }),
+    };
+    let mut image = match args.projection {
+        MapProjection::Equirectangular => map,
+        _ => reproject_map(&map, projection.as_ref()),
+    };
+    let (image_width, image_height) = image.dimensions();
+
+    for (index, track) in tracks.iter().enumerate() {
+        draw_ground_track(&mut image, projection.as_ref(), track, &track_color(index));
+        if !args.footprint.is_empty() {
+            draw_footprints(&mut image, projection.as_ref(), &track[0], &args.footprint, &track_color(index), Some(64));
+        }
+    }
+    for station in &stations {
+        let coordinates = &station.observer.geodetic_coordinates;
+        let point = match gimme_point(projection.as_ref(), &coordinates.longitude, &coordinates.latitude) {
+            Ok(point) => point,
+            Err(_) => continue,
+        };
+        for pixel in &get_pixel_vector(&image_height, &image_width, &point, false)? {
+            color_pixel(&mut image, pixel, &station.color)?;
+        }
+    }
+
+    image.save(&args.output)?;
+    println!("map written to {}", args.output.display());
+    print_tle_ages(&satellites);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_skyplot(args: SkyplotArgs) -> anyhow::Result<ExitCode> {
+    let mut satellites = load_satellites(&args.satellites)?;
+    let stations = stations(&args.observers)?;
+    let (start, end) = time_range(&args.time, &satellites)?;
+    let config = SkyPlotConfig {
+        size: args.size,
+        step: args.time.step.unwrap_or(SkyPlotConfig::default().step),
+    };
+
+    for station in &stations {
+        let image = draw_sky_plot(&station.observer, &mut satellites, &start, &end, &config)?;
+        let output = if stations.len() > 1 {
+            suffixed_path(&args.output, &file_tag(&station.name))
+        } else {
+            args.output.clone()
+        };
+        image.save(&output)?;
+        println!("{} sky plot written to {}", describe_station(station), output.display());
+    }
+    print_tle_ages(&satellites);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_doppler(args: DopplerArgs) -> anyhow::Result<ExitCode> {
+    let mut satellites = load_satellites(&args.satellites)?;
+    let stations = stations(&args.observers)?;
+    let (start, end) = time_range(&args.time, &satellites)?;
+    let step = args.time.step.unwrap_or(TimeDelta::seconds(10));
+    let single = stations.len() == 1 && satellites.len() == 1;
+
+    for station in &stations {
+        for satellite in satellites.iter_mut() {
+            let name = satellite.name();
+            let samples = doppler_series(&station.observer, satellite, &start, &end, &step, &args.frequency)?;
+            match &args.output {
+                Some(output) => {
+                    let output = if single {
+                        output.clone()
+                    } else {
+                        suffixed_path(output, &format!("{}_{}", file_tag(&name), file_tag(&station.name)))
+                    };
+                    let mut writer = BufWriter::new(File::create(&output)?);
+                    write_doppler_table(&mut writer, &samples)?;
+                    writer.flush()?;
+                    println!("{} {} Doppler written to {}", describe_station(station), name, output.display());
+                }
+                None => {
+                    let mut stdout = std::io::stdout().lock();
+                    if !single {
+                        writeln!(stdout, "# {} {}", describe_station(station), name)?;
+                    }
+                    write_doppler_table(&mut stdout, &samples)?;
+                }
+            }
+        }
+    }
+    print_tle_ages(&satellites);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_charts(args: ChartsArgs) -> anyhow::Result<ExitCode> {
+    let mut satellites = load_satellites(&args.satellites)?;
+    let observer = stations(&args.observers)?[0].observer;
+    let plan = args.lcp.as_deref().map(load_lcp).transpose()?;
+    let (start, end) = match (&plan, args.time.start) {
+        // Without an explicit start, chart the plan's handovers.
+        (Some(plan), None) => match (plan.handovers().first(), plan.handovers().last()) {
+            (Some((_, first)), Some((_, last))) if args.time.end.is_none() => (first.start_time, last.end_time),
+            _ => time_range(&args.time, &satellites)?,
+        },
+        _ => time_range(&args.time, &satellites)?,
+    };
+
+    let carrier = match (args.frequency, &plan) {
+        (Some(frequency), _) => CarrierFrequencies { uplink_mhz: frequency, downlink_mhz: frequency },
+        (None, Some(plan)) => plan.handovers()
+            .first()
+            .map(|(_, row)| row.forward_carrier.frequencies())
+            .ok_or_else(|| anyhow::anyhow!("the LCP has no handover rows to take a frequency from, use --frequency"))?,
+        (None, None) => anyhow::bail!("a frequency is needed, use --frequency or --lcp"),
+    };
+    let config = ChartConfig {
+        width: args.width,
+        height: args.height,
+        step: args.time.step.unwrap_or(ChartConfig::new(carrier).step),
+        ..ChartConfig::new(carrier)
+    };
+    let markers = plan.as_ref().map(handover_markers).unwrap_or_default();
+
+    std::fs::create_dir_all(&args.output)?;
+    for quantity in ChartQuantity::ALL {
+        let image = draw_time_series_chart(&observer, &mut satellites, &start, &end, &quantity, &config, &markers)?;
+        let output = args.output.join(format!("{}.png", quantity.file_stem()));
+        image.save(&output)?;
+        println!("{} chart written to {}", quantity.title(), output.display());
+    }
+    print_tle_ages(&satellites);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_validate_lcp(args: ValidateLcpArgs) -> anyhow::Result<ExitCode> {
+    let mut satellites = load_satellites(&args.satellites)?;
+    let plan = load_lcp(&args.lcp)?;
+    let mut registry = SiteRegistry::built_in();
+    if let Some(path) = &args.sites {
+        registry.extend_from_file(path)?;
+    }
+    let gateway = args.gateway.unwrap_or_else(|| plan.gateway_observer(&registry));
+    let terminal = args.terminal.unwrap_or_else(|| plan.terminal_observer(&registry));
+    let config = ValidationConfig {
+        gateway_mask: args.gateway_mask.unwrap_or(gateway.elevation_mask),
+        terminal_mask: args.terminal_mask.unwrap_or(terminal.elevation_mask),
+        step: args.step,
+    };
+
+    println!("plan {} gateway {} terminal {} ({})",
+             plan.link_config_plan_uid, plan.gateway_id, plan.terminal_id, plan.terminal_name);
+    let source = |flag: bool, id: &str, beam: &str| match (flag, registry.get(id)) {
+        (true, _) => "command line".to_string(),
+        (false, Some(_)) => format!("site {}", id),
+        (false, None) => format!("{} beam centre", beam),
+    };
+    for (role, observer, mask, source) in [
+        ("gateway", &gateway, config.gateway_mask, source(args.gateway.is_some(), &plan.gateway_id, "gateway")),
+        ("terminal", &terminal, config.terminal_mask, source(args.terminal.is_some(), &plan.terminal_id, "terminal")),
+    ] {
+        let coordinates = &observer.geodetic_coordinates;
+        println!("{} at ({:.4}, {:.4}, {:.3} km) from {}, mask {} deg",
+                 role, coordinates.latitude, coordinates.longitude, coordinates.altitude, source, mask);
+    }
+    let report = validate_plan(&plan, &mut satellites, &gateway, &terminal, &config)?;
+    print_report(&report);
+    print_tle_ages(&satellites);
+    Ok(if report.is_valid() { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
+
+fn run_constellation(args: ConstellationArgs) -> anyhow::Result<ExitCode> {
+    let config = ConstellationConfig {
+        pattern: match args.pattern {
+            Pattern::Delta => ConstellationPattern::WalkerDelta,
+            Pattern::Star => ConstellationPattern::WalkerStar,
+            Pattern::Ring => ConstellationPattern::Ring,
+        },
+        satellites: args.satellites,
+        planes: args.planes,
+        phasing: args.phasing,
+        altitude: args.altitude,
+        inclination: args.inclination,
+        raan: args.raan,
+        mean_anomaly: args.mean_anomaly,
+        name_prefix: args.name,
+        first_norad_id: args.first_id,
+        launch: Some(args.launch),
+    };
+    let elements = generate_constellation(&config, &args.epoch)?
+        .iter()
+        .map(KeplerianElements::to_elements)
+        .collect::<anyhow::Result<Vec<sgp4::Elements>>>()?;
+
+    match &args.output {
+        Some(output) => {
+            let mut writer = BufWriter::new(File::create(output)?);
+            write_tles(&mut writer, &elements)?;
+            writer.flush()?;
+            println!("{} satellites written to {}", elements.len(), output.display());
+        }
+        None => write_tles(&mut std::io::stdout().lock(), &elements)?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_report(report: &ValidationReport) {
+    let time = |time: &NaiveDateTime| time.format("%Y-%m-%d %H:%M:%S%.3f");
+    for slot in &report.slots {
+        let margin = |check: &ObserverCheck| {
+            let time = check.time_margin().map_or("-".to_string(), |time| format!("{:.3} s", time.num_milliseconds() as f64 / 1000.0));
+            let fail = if check.elevation_margin < 0.0 { " FAIL" } else { "" };
+            format!("{:.1} deg{} / {}", check.elevation_margin, fail, time)
+        };
+        println!("{} {} to {} | gateway min el {:.1} margin {} | terminal min el {:.1} margin {} | {}",
+                 slot.satellite, time(&slot.start), time(&slot.end),
+                 slot.gateway.min_elevation, margin(&slot.gateway),
+                 slot.terminal.min_elevation, margin(&slot.terminal),
+                 if slot.is_valid() { "ok" } else { "VIOLATION" });
+        for (role, check) in [("gateway", &slot.gateway), ("terminal", &slot.terminal)] {
+            for (from, to) in &check.uncovered {
+                println!("    {} below mask {} to {}", role, time(from), time(to));
+            }
+        }
+    }
+    for transition in report.gaps() {
+        println!("gap {:.3} s between {} ending {} and {} starting {}",
+                 transition.gap.num_milliseconds() as f64 / 1000.0, transition.from_satellite, time(&transition.from_end),
+                 transition.to_satellite, time(&transition.to_start));
+    }
+    for transition in report.overlaps() {
+        println!("overlap {:.3} s between {} ending {} and {} starting {}",
+                 -transition.gap.num_milliseconds() as f64 / 1000.0, transition.from_satellite, time(&transition.from_end),
+                 transition.to_satellite, time(&transition.to_start));
+    }
+    for name in &report.missing_satellites {
+        println!("satellite {} is in the plan but not in the TLE file", name);
+    }
+    println!("{} slots, {} violations, {} gaps, {} overlaps, {} missing satellites: {}",
+             report.slots.len(), report.violations().len(), report.gaps().len(), report.overlaps().len(),
+             report.missing_satellites.len(), if report.is_valid() { "valid" } else { "INVALID" });
+}
+
+/// Orbit file used when no file and no what-if orbits are given.
+const DEFAULT_ORBIT_FILE: &str = "common/tle2.txt";
+
+/// load_satellites: reads a 2LE/3LE, OMM (JSON, XML, CSV, KVN), OEM or SP3 file and adds the --kepler and --walker
+/// orbits, keeping only the requested satellites when names are given, with the propagator, staleness policy
+/// and interpolation from the flags. Records that do not parse are reported on stderr and skipped.
+fn load_satellites(args: &SatelliteArgs) -> anyhow::Result<Vec<Satellite>> {
+    let what_if = what_if_orbits(args)?;
+    let path = match &args.tle {
+        Some(path) => Some(path.clone()),
+        None if what_if.is_empty() => Some(PathBuf::from(DEFAULT_ORBIT_FILE)),
+        None => None,
+    };
+    let source = match &path {
+        Some(path) if what_if.is_empty() => path.display().to_string(),
+        Some(path) => format!("{} or the what-if orbits", path.display()),
+        None => "the what-if orbits".to_string(),
+    };
+    let wanted = |name: &String| args.satellites.is_empty() || args.satellites.contains(name);
+
+    let mut satellites: Vec<Satellite> = Vec::new();
+    if let Some(path) = &path {
+        let set = load_orbit_file(path)?;
+        for error in &set.errors {
+            eprintln!("warning: {} {}, record skipped", path.display(), error);
+        }
+        for satellite in set.satellites.into_iter().filter(|satellite| wanted(&satellite.name())) {
+            satellites.push(match args.propagator {
+                Some(kind) => repropagate(satellite, kind, args.integration_step)?,
+                None => satellite,
+            });
+        }
+    }
+    for elements in what_if.iter().filter(|elements| wanted(&elements.name)) {
+        satellites.push(keplerian_satellite(elements, args)?);
+    }
+    let staleness = StalenessPolicy {
+        max_age: args.max_tle_age,
+        action: match args.stale {
+            StaleAction::Warn => StalenessAction::Warn,
+            StaleAction::Refuse => StalenessAction::Refuse,
+            StaleAction::Widen => StalenessAction::WidenMargin,
+        },
+        margin_per_day: args.stale_margin,
+    };
+    for satellite in satellites.iter_mut() {
+        satellite.staleness = staleness;
+        if let Some(ephemeris) = satellite.propagator.ephemeris_mut() {
+            if args.interpolation.is_some() || args.interpolation_points.is_some() {
+                let (file_interpolation, file_points) = ephemeris.interpolation();
+                let interpolation = match args.interpolation {
+                    Some(InterpolationMethod::Lagrange) => Interpolation::Lagrange,
+                    Some(InterpolationMethod::Hermite) => Interpolation::Hermite,
+                    None => file_interpolation,
+                };
+                let points = match (args.interpolation_points, interpolation == file_interpolation) {
+                    (Some(points), _) => points,
+                    (None, true) => file_points,
+                    (None, false) if interpolation == Interpolation::Hermite => DEFAULT_HERMITE_POINTS,
+                    (None, false) => DEFAULT_LAGRANGE_POINTS,
+                };
+                ephemeris.set_interpolation(interpolation, points)?;
+            }
+        }
+    }
+
+    for name in &args.satellites {
+        if !satellites.iter().any(|satellite| satellite.name() == *name) {
+            anyhow::bail!("satellite {} is not in {}", name, source);
+        }
+    }
+    if satellites.is_empty() {
+        anyhow::bail!("no satellites in {}", source);
+    }
+    Ok(satellites)
+}
+
+/// what_if_orbits: elements of the --kepler satellites (NORAD ids from 99001) then the --walker constellation,
+/// at --orbit-epoch.
+fn what_if_orbits(args: &SatelliteArgs) -> anyhow::Result<Vec<KeplerianElements>> {
+    let Some(epoch) = args.orbit_epoch else {
+        return Ok(Vec::new());
+    };
+    let mut orbits: Vec<KeplerianElements> = args.keplerian.iter()
+        .enumerate()
+        .map(|(index, orbit)| KeplerianElements {
+            name: orbit.name.clone(),
+            norad_id: 99_001 + index as u64,
+            international_designator: None,
+            epoch,
+            semi_major_axis: orbit.semi_major_axis,
+            eccentricity: orbit.eccentricity,
+            inclination: orbit.inclination,
+            right_ascension: orbit.right_ascension,
+            argument_of_perigee: orbit.argument_of_perigee,
+            mean_anomaly: orbit.mean_anomaly,
+            drag_term: 0.0,
+        })
+        .collect();
+    if let Some(config) = &args.walker {
+        orbits.extend(generate_constellation(config, &epoch)?);
+    }
+    Ok(orbits)
+}
+
+/// keplerian_satellite: a what-if satellite flown by sgp4, or by --propagator from the elements read as osculating.
+fn keplerian_satellite(elements: &KeplerianElements, args: &SatelliteArgs) -> anyhow::Result<Satellite> {
+    let propagator: Box<dyn Propagator> = match args.propagator {
+        None => return elements.to_satellite(),
+        Some(PropagatorKind::TwoBody) => Box::new(TwoBodyPropagator::from_keplerian(elements)?),
+        Some(PropagatorKind::Numerical) => Box::new(NumericalPropagator::from_keplerian(elements, args.integration_step)?),
+    };
+    Ok(Satellite::with_propagator(propagator))
+}
+
+/// repropagate: the satellite moved by another propagator, starting from its state at its epoch.
+/// The new satellite has no sgp4 elements, so no TLE age, and no ephemeris span.
+fn repropagate(mut satellite: Satellite, kind: PropagatorKind, step: f64) -> anyhow::Result<Satellite> {
+    let (name, epoch) = (satellite.name(), satellite.reference_epoch());
+    let state = satellite.propagator.state_at(&epoch)?;
+    let propagator: Box<dyn Propagator> = match kind {
+        PropagatorKind::TwoBody => Box::new(TwoBodyPropagator::new(&name, epoch, state.position, state.velocity)),
+        PropagatorKind::Numerical => Box::new(NumericalPropagator::new(&name, epoch, state.position, state.velocity, step)?),
+    };
+    Ok(Satellite::with_propagator(propagator))
+}
+
+/// print_tle_ages: summary of the TLE ages the predictions were made with, on stderr so tables on
+/// stdout stay clean. Every stale satellite gets its own line.
+fn print_tle_ages(satellites: &[Satellite]) {
+    let ranges: Vec<(TimeDelta, TimeDelta)> = satellites.iter().filter_map(Satellite::age_range).collect();
+    let (Some(oldest), Some(newest)) = (ranges.iter().map(|range| range.0).min(), ranges.iter().map(|range| range.1).max()) else {
+        return;
+    };
+    let stale: Vec<&Satellite> = satellites.iter().filter(|satellite| satellite.is_stale()).collect();
+    let limit = days(&satellites[0].staleness.max_age);
+    eprintln!("TLE age {:+.2} to {:+.2} days from epoch over {} satellites, {} stale (limit {} days)",
+              days(&oldest), days(&newest), ranges.len(), stale.len(), limit);
+    for satellite in stale {
+        if let Some((min, max)) = satellite.age_range() {
+            let margin = satellite.staleness.elevation_margin(&min.abs().max(max.abs()));
+            let widened = if margin > 0.0 { format!(", masks raised by up to {:.1} deg", margin) } else { String::new() };
+            eprintln!("warning: {} TLE epoch {} is STALE, used {:+.2} to {:+.2} days from epoch{}",
+                      satellite.name(), satellite.reference_epoch().format("%Y-%m-%d %H:%M:%S"),
+                      days(&min), days(&max), widened);
+        }
+    }
+}
+
+/// stations: the sites named with --site (every site of the site file when none are named)
+/// followed by the observers from the flags, with the mask flag applied. The origin when there are none.
+fn stations(args: &ObserverArgs) -> anyhow::Result<Vec<Station>> {
+    let mut registry = SiteRegistry::built_in();
+    let mut file_sites = Vec::new();
+    if let Some(path) = &args.sites {
+        file_sites = load_sites(path)?;
+        registry.extend(file_sites.clone());
+    }
+    let sites = if args.site_ids.is_empty() {
+        file_sites
+    } else {
+        args.site_ids.iter()
+            .map(|id| registry.site(id).cloned())
+            .collect::<anyhow::Result<Vec<Site>>>()?
+    };
+
+    let mut stations: Vec<Station> = sites.into_iter()
+        .map(|site| Station { name: site.id, observer: site.observer, color: site.color })
+        .collect();
+    for (index, observer) in args.observers.iter().enumerate() {
+        stations.push(Station {
+            name: format!("observer {}", index + 1),
+            observer: *observer,
+            color: DEFAULT_SITE_COLOR,
+        });
+    }
+    if stations.is_empty() {
+        stations.push(Station { name: "observer 1".to_string(), observer: Observer::new(), color: DEFAULT_SITE_COLOR });
+    }
+    if let Some(mask) = args.mask {
+        for station in stations.iter_mut() {
+            station.observer.elevation_mask = mask;
+        }
+    }
+    Ok(stations)
+}
+
+/// time_range: start and end from the flags, defaulting to the epoch of the first satellite
+/// (the start of its ephemeris). A default end stops where the first ephemeris to run out ends.
+fn time_range(args: &TimeArgs, satellites: &[Satellite]) -> anyhow::Result<(NaiveDateTime, NaiveDateTime)> {
+    let start = args.start.unwrap_or(satellites[0].reference_epoch());
+    let end = match args.end {
+        Some(end) => end,
+        None => {
+            let end = start + TimeDelta::milliseconds((args.duration * 3_600_000.0).round() as i64);
+            satellites.iter()
+                .filter_map(|satellite| satellite.valid_span().map(|span| span.1))
+                .fold(end, NaiveDateTime::min)
+        }
+    };
+    if end <= start {
+        anyhow::bail!("end of the time range {} is not after its start {}", end, start);
+    }
+    Ok((start, end))
+}
+
+fn describe_station(station: &Station) -> String {
+    let coordinates = &station.observer.geodetic_coordinates;
+    format!("{} ({:.4}, {:.4}, {:.3} km, mask {} deg)",
+            station.name, coordinates.latitude, coordinates.longitude, coordinates.altitude, station.observer.elevation_mask)
+}
+
+/// file_tag: a name made safe for use in a file name.
+fn file_tag(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect()
+}
+
+/// suffixed_path: "dir/name.ext" to "dir/name_suffix.ext".
+fn suffixed_path(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(extension) => format!("{}_{}.{}", stem, suffix, extension.to_string_lossy()),
+        None => format!("{}_{}", stem, suffix),
+    };
+    path.with_file_name(name)
+}
@@ -0,0 +1,526 @@
+//! purpose:
+//!     cli holds the command line interface of the satview binary.
+//!     mod.rs defines the subcommands and their flags, commands.rs runs them.
+
+pub mod commands;
+
+use std::path::PathBuf;
+use chrono::{DateTime, NaiveDateTime, TimeDelta};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use satview::obs::observer::Observer;
+use satview::sat::constellation::{ConstellationConfig, ConstellationPattern};
+use satview::sat::numerical::DEFAULT_INTEGRATION_STEP;
+
+/// Satellite visibility windows, maps, sky plots and link geometry from TLEs.
+#[derive(Parser)]
+#[command(name = "satview", version, about, long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print visibility windows (AOS, culmination, LOS) for every observer and satellite
+    Passes(PassesArgs),
+    /// Draw ground tracks, footprints and observers on an earth map
+    Map(MapArgs),
+    /// Draw a polar azimuth/elevation sky plot for every observer
+    Skyplot(SkyplotArgs),
+    /// Write Doppler offset and rate tables (CSV) for every observer and satellite
+    Doppler(DopplerArgs),
+    /// Draw elevation, slant range, path loss and Doppler charts for the first observer
+    Charts(ChartsArgs),
+    /// Check an LCP handover schedule against gateway and terminal geometry
+    ValidateLcp(ValidateLcpArgs),
+    /// Generate a Walker-delta, Walker-star or ring constellation as 3LE
+    Constellation(ConstellationArgs),
+}
+
+/// Where the satellites come from.
+#[derive(Args)]
+pub struct SatelliteArgs {
+    /// Orbit file with the satellites: 2LE/3LE, OMM as JSON, XML, CSV or KVN, or a CCSDS OEM or SP3 ephemeris
+    /// (detected from the content) [default: common/tle2.txt when there are no --kepler or --walker orbits]
+    #[arg(short, long, value_name = "FILE")]
+    pub tle: Option<PathBuf>,
+    /// What-if satellite from Keplerian elements at --orbit-epoch: semi-major axis in km, eccentricity,
+    /// inclination, RAAN, argument of perigee and mean anomaly in degrees, can be repeated
+    #[arg(long = "kepler", value_name = "NAME,A,E,I,RAAN,ARGP,M",
+          value_parser = parse_kepler, allow_hyphen_values = true, requires = "orbit_epoch")]
+    pub keplerian: Vec<KeplerOrbit>,
+    /// What-if constellation at --orbit-epoch: delta, star or ring, total/planes/phasing, altitude in km and
+    /// inclination in degrees, e.g. delta,24/3/1,1200,55. Satellites are named W01, W02, ...
+    #[arg(long, value_name = "PATTERN,T/P/F,ALT,INC", value_parser = parse_walker, requires = "orbit_epoch")]
+    pub walker: Option<ConstellationConfig>,
+    /// Epoch in UTC of the --kepler and --walker orbits
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub orbit_epoch: Option<NaiveDateTime>,
+    /// Only use the satellite with this name (3LE name line, OMM/OEM OBJECT_NAME or SP3 id), can be repeated
+    #[arg(short, long = "satellite", value_name = "NAME")]
+    pub satellites: Vec<String>,
+    /// Largest distance in days between a prediction and the TLE epoch before the TLE counts as stale
+    #[arg(long, value_name = "DAYS", value_parser = parse_days, default_value = "14", allow_hyphen_values = true)]
+    pub max_tle_age: TimeDelta,
+    /// What to do with predictions from a stale TLE
+    #[arg(long, value_enum, default_value = "warn")]
+    pub stale: StaleAction,
+    /// Elevation mask increase per day beyond --max-tle-age, with --stale widen
+    #[arg(long, value_name = "DEG", default_value_t = 1.0)]
+    pub stale_margin: f64,
+    /// Interpolation of ephemeris files [default: the OEM's INTERPOLATION, Lagrange for SP3]
+    #[arg(long, value_enum)]
+    pub interpolation: Option<InterpolationMethod>,
+    /// Samples per ephemeris interpolation [default: the OEM's INTERPOLATION_DEGREE, 8 for Lagrange, 4 for Hermite]
+    #[arg(long, value_name = "N")]
+    pub interpolation_points: Option<usize>,
+    /// Propagate with this model instead, from each satellite's state at its epoch
+    /// [default: sgp4 for elements, interpolation for ephemerides]
+    #[arg(long, value_enum)]
+    pub propagator: Option<PropagatorKind>,
+    /// Integration step in seconds of --propagator numerical
+    #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_INTEGRATION_STEP)]
+    pub integration_step: f64,
+}
+
+/// A --kepler orbit, km and degrees.
+#[derive(Clone)]
+pub struct KeplerOrbit {
+    pub name: String,
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub right_ascension: f64,
+    pub argument_of_perigee: f64,
+    pub mean_anomaly: f64,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum StaleAction {
+    /// Use the prediction and report the TLE age
+    Warn,
+    /// Fail the command
+    Refuse,
+    /// Raise the elevation mask of visibility checks by --stale-margin per day over the limit
+    Widen,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum InterpolationMethod {
+    /// Polynomial through the positions
+    Lagrange,
+    /// Polynomial through the positions and velocities, needs velocities in the file
+    Hermite,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum PropagatorKind {
+    /// Kepler motion around a point mass earth
+    TwoBody,
+    /// Runge-Kutta integration with the J2, J3 and J4 zonal terms
+    Numerical,
+}
+
+/// Who is looking.
+#[derive(Args)]
+pub struct ObserverArgs {
+    /// Observer as LAT,LON[,ALT] in degrees and km, can be repeated [default: 0,0 without sites]
+    #[arg(long = "observer", value_name = "LAT,LON[,ALT]",
+          value_parser = parse_observer, allow_hyphen_values = true)]
+    pub observers: Vec<Observer>,
+    /// Site file (JSON) adding to the built-in sites, all of its sites are used when no --site is given
+    #[arg(long, value_name = "FILE")]
+    pub sites: Option<PathBuf>,
+    /// Use the known site with this id, built-in (e.g. GS_US_PHX) or from --sites, can be repeated
+    #[arg(long = "site", value_name = "ID")]
+    pub site_ids: Vec<String>,
+    /// Elevation mask in degrees, applied to every observer [default: 15, or the site's own mask]
+    #[arg(short, long, value_name = "DEG", allow_hyphen_values = true)]
+    pub mask: Option<f64>,
+}
+
+/// When to look.
+#[derive(Args)]
+pub struct TimeArgs {
+    /// Start of the time range in UTC, e.g. 2024-04-10T13:00:00Z [default: epoch of the first satellite]
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub start: Option<NaiveDateTime>,
+    /// End of the time range in UTC [default: start + duration]
+    #[arg(long, value_name = "TIME", value_parser = parse_time, conflicts_with = "duration")]
+    pub end: Option<NaiveDateTime>,
+    /// Length of the time range in hours, used when no end is given
+    #[arg(short, long, value_name = "HOURS", default_value_t = 24.0)]
+    pub duration: f64,
+    /// Sampling step in seconds [default depends on the command]
+    #[arg(long, value_name = "SECONDS", value_parser = parse_step, allow_hyphen_values = true)]
+    pub step: Option<TimeDelta>,
+}
+
+#[derive(Args)]
+pub struct PassesArgs {
+    #[command(flatten)]
+    pub satellites: SatelliteArgs,
+    #[command(flatten)]
+    pub observers: ObserverArgs,
+    #[command(flatten)]
+    pub time: TimeArgs,
+    /// Also print the windows where every observer sees the same satellite (two or more observers)
+    #[arg(long)]
+    pub joint: bool,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum MapProjection {
+    Equirectangular,
+    WebMercator,
+    /// Azimuthal equidistant centred on the first observer
+    Azimuthal,
+}
+
+#[derive(Args)]
+pub struct MapArgs {
+    #[command(flatten)]
+    pub satellites: SatelliteArgs,
+    #[command(flatten)]
+    pub observers: ObserverArgs,
+    #[command(flatten)]
+    pub time: TimeArgs,
+    /// Equirectangular earth image to draw on
+    #[arg(long, value_name = "FILE", default_value = "common/BigEarth.jpg")]
+    pub map: PathBuf,
+    /// Output projection
+    #[arg(short, long, value_enum, default_value = "equirectangular")]
+    pub projection: MapProjection,
+    /// Footprint minimum elevations in degrees, drawn at the start of each track
+    #[arg(short, long, value_name = "DEG", value_delimiter = ',', default_value = "10,20,30")]
+    pub footprint: Vec<f64>,
+    /// Output PNG
+    #[arg(short, long, value_name = "FILE", default_value = "out.png")]
+    pub output: PathBuf,
+}
+
+#[derive(Args)]
+pub struct SkyplotArgs {
+    #[command(flatten)]
+    pub satellites: SatelliteArgs,
+    #[command(flatten)]
+    pub observers: ObserverArgs,
+    #[command(flatten)]
+    pub time: TimeArgs,
+    /// Width and height of the plot in pixels
+    #[arg(long, value_name = "PIXELS", default_value_t = 800)]
+    pub size: u32,
+    /// Output PNG, suffixed with the observer name when there is more than one
+    #[arg(short, long, value_name = "FILE", default_value = "skyplot.png")]
+    pub output: PathBuf,
+}
+
+#[derive(Args)]
+pub struct DopplerArgs {
+    #[command(flatten)]
+    pub satellites: SatelliteArgs,
+    #[command(flatten)]
+    pub observers: ObserverArgs,
+    #[command(flatten)]
+    pub time: TimeArgs,
+    /// Carrier frequency in MHz
+    #[arg(short = 'F', long, value_name = "MHZ")]
+    pub frequency: f64,
+    /// Output CSV, one file per observer and satellite [default: standard output]
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ChartsArgs {
+    #[command(flatten)]
+    pub satellites: SatelliteArgs,
+    #[command(flatten)]
+    pub observers: ObserverArgs,
+    #[command(flatten)]
+    pub time: TimeArgs,
+    /// Downlink frequency in MHz for path loss and Doppler [default: forward carrier of the first LCP slot]
+    #[arg(short = 'F', long, value_name = "MHZ", required_unless_present = "lcp")]
+    pub frequency: Option<f64>,
+    /// LCP whose handovers are drawn as vertical markers
+    #[arg(long, value_name = "FILE")]
+    pub lcp: Option<PathBuf>,
+    /// Chart width in pixels
+    #[arg(long, value_name = "PIXELS", default_value_t = 1200)]
+    pub width: u32,
+    /// Chart height in pixels
+    #[arg(long, value_name = "PIXELS", default_value_t = 400)]
+    pub height: u32,
+    /// Output directory, one PNG per quantity
+    #[arg(short, long, value_name = "DIR", default_value = ".")]
+    pub output: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ValidateLcpArgs {
+    #[command(flatten)]
+    pub satellites: SatelliteArgs,
+    /// Link config plan (JSON)
+    #[arg(short, long, value_name = "FILE")]
+    pub lcp: PathBuf,
+    /// Site file (JSON) adding to the built-in sites that gatewayID and terminalID are looked up in
+    #[arg(long, value_name = "FILE")]
+    pub sites: Option<PathBuf>,
+    /// Gateway as LAT,LON[,ALT] [default: the site named by gatewayID, else the gateway beam centre]
+    #[arg(long, value_name = "LAT,LON[,ALT]", value_parser = parse_observer, allow_hyphen_values = true)]
+    pub gateway: Option<Observer>,
+    /// Terminal as LAT,LON[,ALT] [default: the site named by terminalID, else the terminal beam centre]
+    #[arg(long, value_name = "LAT,LON[,ALT]", value_parser = parse_observer, allow_hyphen_values = true)]
+    pub terminal: Option<Observer>,
+    /// Gateway elevation mask in degrees [default: the gateway site's mask, else 15]
+    #[arg(long, value_name = "DEG")]
+    pub gateway_mask: Option<f64>,
+    /// Terminal elevation mask in degrees [default: the terminal site's mask, else 15]
+    #[arg(long, value_name = "DEG")]
+    pub terminal_mask: Option<f64>,
+    /// Elevation sampling step inside each slot in seconds
+    #[arg(long, value_name = "SECONDS", value_parser = parse_step, default_value = "10", allow_hyphen_values = true)]
+    pub step: TimeDelta,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum Pattern {
+    /// Walker-delta, planes spread over 360 degrees of RAAN
+    Delta,
+    /// Walker-star, planes spread over 180 degrees of RAAN
+    Star,
+    /// A single plane
+    Ring,
+}
+
+#[derive(Args)]
+pub struct ConstellationArgs {
+    /// Layout of the orbital planes
+    #[arg(short, long, value_enum, default_value = "ring")]
+    pub pattern: Pattern,
+    /// Total number of satellites
+    #[arg(short = 'n', long, value_name = "COUNT", default_value_t = 6)]
+    pub satellites: u32,
+    /// Number of orbital planes, must divide the number of satellites
+    #[arg(long, value_name = "COUNT", default_value_t = 1)]
+    pub planes: u32,
+    /// Walker phasing factor, 0 to planes - 1
+    #[arg(short = 'f', long, value_name = "F", default_value_t = 0)]
+    pub phasing: u32,
+    /// Circular orbit altitude above the equator in km, as the sgp4 mean semi-major axis
+    #[arg(short, long, value_name = "KM", default_value_t = 8067.0)]
+    pub altitude: f64,
+    /// Inclination in degrees
+    #[arg(short, long, value_name = "DEG", default_value_t = 0.0)]
+    pub inclination: f64,
+    /// Right ascension of the first plane in degrees
+    #[arg(long, value_name = "DEG", default_value_t = 0.0, allow_hyphen_values = true)]
+    pub raan: f64,
+    /// Mean anomaly of the first satellite in degrees
+    #[arg(long, value_name = "DEG", default_value_t = 0.0, allow_hyphen_values = true)]
+    pub mean_anomaly: f64,
+    /// Epoch of the elements in UTC
+    #[arg(short, long, value_name = "TIME", value_parser = parse_time)]
+    pub epoch: NaiveDateTime,
+    /// Name prefix, satellites are numbered after it
+    #[arg(long, value_name = "PREFIX", default_value = "MP")]
+    pub name: String,
+    /// NORAD id of the first satellite
+    #[arg(long, value_name = "ID", default_value_t = 99901)]
+    pub first_id: u64,
+    /// Launch part of the international designator, pieces A, B, ... are appended
+    #[arg(long, value_name = "YYYY-NNN", default_value = "2022-999")]
+    pub launch: String,
+    /// Output 3LE file [default: standard output]
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+/// parse_observer: observer from "LAT,LON" or "LAT,LON,ALT" (degrees, km).
+fn parse_observer(text: &str) -> Result<Observer, String> {
+    let values = text.split(',')
+        .map(|value| value.trim().parse::<f64>().map_err(|err| format!("{:?} is not a number: {}", value, err)))
+        .collect::<Result<Vec<f64>, String>>()?;
+    let (latitude, longitude, altitude) = match values[..] {
+        [latitude, longitude] => (latitude, longitude, 0.0),
+        [latitude, longitude, altitude] => (latitude, longitude, altitude),
+        _ => return Err("expected LAT,LON or LAT,LON,ALT".to_string()),
+    };
+    if !(-90.0..=90.0).contains(&latitude) {
+        return Err(format!("latitude {} is outside -90 to 90", latitude));
+    }
+    if !(-180.0..=360.0).contains(&longitude) {
+        return Err(format!("longitude {} is outside -180 to 360", longitude));
+    }
+    if !altitude.is_finite() {
+        return Err(format!("altitude {} is not a number of km", altitude));
+    }
+    Ok(Observer::from_coordinates([latitude, longitude, altitude]))
+}
+
+/// parse_kepler: a --kepler orbit from "NAME,A,E,I,RAAN,ARGP,M".
+fn parse_kepler(text: &str) -> Result<KeplerOrbit, String> {
+    let (name, rest) = text.split_once(',').ok_or("expected NAME,A,E,I,RAAN,ARGP,M")?;
+    let values = rest.split(',')
+        .map(|value| value.trim().parse::<f64>().map_err(|err| format!("{:?} is not a number: {}", value, err)))
+        .collect::<Result<Vec<f64>, String>>()?;
+    match values[..] {
+        [semi_major_axis, eccentricity, inclination, right_ascension, argument_of_perigee, mean_anomaly] if !name.trim().is_empty() => {
+            Ok(KeplerOrbit {
+                name: name.trim().to_string(),
+                semi_major_axis,
+                eccentricity,
+                inclination,
+                right_ascension,
+                argument_of_perigee,
+                mean_anomaly,
+            })
+        }
+        _ => Err("expected NAME,A,E,I,RAAN,ARGP,M".to_string()),
+    }
+}
+
+/// parse_walker: a --walker constellation from "PATTERN,T/P/F,ALT,INC".
+fn parse_walker(text: &str) -> Result<ConstellationConfig, String> {
+    let fields: Vec<&str> = text.split(',').map(str::trim).collect();
+    let [pattern, layout, altitude, inclination] = fields[..] else {
+        return Err("expected PATTERN,T/P/F,ALT,INC".to_string());
+    };
+    let pattern = match pattern {
+        "delta" => ConstellationPattern::WalkerDelta,
+        "star" => ConstellationPattern::WalkerStar,
+        "ring" => ConstellationPattern::Ring,
+        _ => return Err(format!("{:?} is not delta, star or ring", pattern)),
+    };
+    let counts = layout.split('/')
+        .map(|value| value.parse::<u32>().map_err(|err| format!("{:?} is not a whole number: {}", value, err)))
+        .collect::<Result<Vec<u32>, String>>()?;
+    let [satellites, planes, phasing] = counts[..] else {
+        return Err(format!("{:?} is not T/P/F", layout));
+    };
+    let number = |value: &str| value.parse::<f64>().map_err(|err| format!("{:?} is not a number: {}", value, err));
+    Ok(ConstellationConfig {
+        pattern,
+        satellites,
+        planes,
+        phasing,
+        altitude: number(altitude)?,
+        inclination: number(inclination)?,
+        name_prefix: "W".to_string(),
+        launch: None,
+        ..Default::default()
+    })
+}
+
+/// parse_time: UTC time from RFC 3339, or without an offset as YYYY-MM-DDTHH:MM[:SS] (T or space).
+fn parse_time(text: &str) -> Result<NaiveDateTime, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.naive_utc());
+    }
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .ok_or_else(|| format!("{:?} is not a UTC time, use e.g. 2024-04-10T13:00:00Z", text))
+}
+
+/// parse_days: positive duration in (fractional) days.
+fn parse_days(text: &str) -> Result<TimeDelta, String> {
+    let days = text.parse::<f64>().map_err(|err| format!("{:?} is not a number: {}", text, err))?;
+    if days.is_nan() || days <= 0.0 {
+        return Err("days must be positive".to_string());
+    }
+    milliseconds(days * 86_400_000.0).ok_or_else(|| format!("{} days is too long", days))
+}
+
+/// parse_step: positive step in (fractional) seconds.
+fn parse_step(text: &str) -> Result<TimeDelta, String> {
+    let seconds = text.parse::<f64>().map_err(|err| format!("{:?} is not a number: {}", text, err))?;
+    if seconds.is_nan() || seconds <= 0.0 {
+        return Err("step must be positive".to_string());
+    }
+    milliseconds(seconds * 1000.0).ok_or_else(|| format!("step {} s is too long", seconds))
+}
+
+/// milliseconds: duration of a positive number of milliseconds rounded to whole ones (at least one),
+/// None when it is out of range (a thousand years and more).
+fn milliseconds(milliseconds: f64) -> Option<TimeDelta> {
+    if milliseconds >= 1000.0 * 365.25 * 86_400_000.0 {
+        return None;
+    }
+    TimeDelta::try_milliseconds(milliseconds.round().max(1.0) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::error::ErrorKind;
+
+    /// parse: the passes command with extra flags.
+    fn parse(flags: &[&str]) -> Result<PassesArgs, clap::Error> {
+        let cli = Cli::try_parse_from(["satview", "passes"].iter().chain(flags))?;
+        match cli.command {
+            Command::Passes(args) => Ok(args),
+            _ => unreachable!(),
+        }
+    }
+
+    fn rejected(flags: &[&str]) -> String {
+        let error = parse(flags).err().unwrap_or_else(|| panic!("{:?} was accepted", flags));
+        assert_eq!(error.kind(), ErrorKind::ValueValidation, "{:?}: {}", flags, error);
+        error.to_string()
+    }
+
+    #[test]
+    fn valid_values_parse() {
+        let args = parse(&["--observer", "33.4484,-112.0740,0.331", "--observer", "-45,200",
+                           "--start", "2024-04-10T13:00:00Z", "--step", "0.5",
+                           "--max-tle-age", "1.5", "--orbit-epoch", "2024-04-10 12:00",
+                           "--kepler", "MP01,14446.256,0,0.1,-10,0,0", "--walker", "delta,24/3/1,1200,53"]).unwrap();
+        let observer = args.observers.observers[0].geodetic_coordinates;
+        assert_eq!((observer.latitude, observer.longitude, observer.altitude), (33.4484, -112.0740, 0.331));
+        assert_eq!(args.observers.observers[1].geodetic_coordinates.longitude, 200.0);
+        let start = NaiveDateTime::parse_from_str("2024-04-10 13:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(args.time.start, Some(start));
+        assert_eq!(args.time.step, Some(TimeDelta::milliseconds(500)));
+        assert_eq!(args.satellites.max_tle_age, TimeDelta::hours(36));
+        assert_eq!(args.satellites.orbit_epoch, Some(start - TimeDelta::hours(1)));
+        let kepler = &args.satellites.keplerian[0];
+        assert_eq!((kepler.name.as_str(), kepler.semi_major_axis, kepler.right_ascension), ("MP01", 14446.256, -10.0));
+        let walker = args.satellites.walker.unwrap();
+        assert_eq!((walker.pattern, walker.satellites, walker.planes, walker.phasing), (ConstellationPattern::WalkerDelta, 24, 3, 1));
+        assert_eq!((walker.altitude, walker.inclination), (1200.0, 53.0));
+    }
+
+    #[test]
+    fn out_of_range_and_malformed_values_are_clap_errors() {
+        assert!(rejected(&["--observer", "91,0"]).contains("latitude 91 is outside -90 to 90"));
+        assert!(rejected(&["--observer", "-90.5,0"]).contains("latitude"));
+        assert!(rejected(&["--observer", "0,-181"]).contains("longitude -181 is outside -180 to 360"));
+        assert!(rejected(&["--observer", "0,361"]).contains("longitude"));
+        assert!(rejected(&["--observer", "nan,0"]).contains("latitude"));
+        assert!(rejected(&["--observer", "0,0,inf"]).contains("altitude"));
+        assert!(rejected(&["--observer", "33.4"]).contains("expected LAT,LON"));
+        assert!(rejected(&["--observer", "north,west"]).contains("is not a number"));
+
+        for walker in ["delta,24/3,1200,53", "delta,24/3/1/0,1200,53", "delta,24:3:1,1200,53",
+                       "delta,-24/3/1,1200,53", "delta,24/3/1,1200", "hexagon,24/3/1,1200,53"] {
+            rejected(&["--orbit-epoch", "2024-04-10T12:00:00Z", "--walker", walker]);
+        }
+        assert!(rejected(&["--orbit-epoch", "2024-04-10T12:00:00Z", "--walker", "delta,24/3,1200,53"]).contains("is not T/P/F"));
+        rejected(&["--orbit-epoch", "2024-04-10T12:00:00Z", "--kepler", "MP01,7000,0,98,0,0"]);
+        rejected(&["--orbit-epoch", "2024-04-10T12:00:00Z", "--kepler", ",7000,0,98,0,0,0"]);
+
+        for time in ["2024-04-10", "10/04/2024 13:00", "2024-13-01T00:00:00Z", "yesterday"] {
+            assert!(rejected(&["--start", time]).contains("is not a UTC time"), "{}", time);
+        }
+
+        for step in ["0", "-10", "nan", "1e300"] {
+            rejected(&["--step", step]);
+        }
+        for days in ["0", "-1", "nan", "inf", "1e20"] {
+            rejected(&["--max-tle-age", days]);
+        }
+        assert!(rejected(&["--step", "0"]).contains("step must be positive"));
+        assert!(rejected(&["--max-tle-age", "-1"]).contains("days must be positive"));
+    }
+}
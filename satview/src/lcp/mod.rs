@@ -0,0 +1,2 @@
+pub mod plan;
+pub mod validate;
\ No newline at end of file
@@ -0,0 +1,238 @@
+//! purpose:
+//!     plan.rs defines the link configuration plan (LCP) format and its loader.
+//!     An LCP pairs a gateway and a terminal, lists which satellite carries the link
+//!     when (handOverRows) and the forward/return carriers used during each slot.
+//!     Field names follow the JSON exactly, unknown fields are rejected. Every field must be
+//!     present, the Option ones (beamGridID, beamContour, the modem powers) may be null.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use chrono::{DateTime, NaiveDateTime};
+use serde::{Deserialize, Deserializer};
+use crate::link::path_loss::CarrierFrequencies;
+use crate::obs::observer::Observer;
+use crate::obs::observer_locations::SiteRegistry;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LinkConfigPlan {
+    #[serde(rename = "linkConfigPlanUID")]
+    pub link_config_plan_uid: String,
+    #[serde(rename = "gatewayID")]
+    pub gateway_id: String,
+    #[serde(rename = "beamGridID", deserialize_with = "Option::deserialize")]
+    pub beam_grid_id: Option<String>,
+    #[serde(rename = "terminalID")]
+    pub terminal_id: String,
+    pub terminal_name: String,
+    pub terminal_mobile_target: bool,
+    #[serde(deserialize_with = "deserialize_utc")]
+    pub effectivity_time: NaiveDateTime,
+    pub gateway_beam: Beam,
+    pub terminal_beam: Beam,
+    /// Handover slots keyed by satellite name (the 3LE name line, e.g. MP01).
+    pub hand_over_rows: BTreeMap<String, Vec<HandOverRow>>,
+    pub link_budget_summary: LinkBudgetSummary,
+}
+
+/// Latitude and longitude in degrees. LCP longitudes are given in [0, 360).
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LatLon {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl LatLon {
+    /// longitude_180: the longitude wrapped to [-180, 180).
+    pub fn longitude_180(&self) -> f64 {
+        (self.longitude + 180.0).rem_euclid(360.0) - 180.0
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Beam {
+    pub beam_center: LatLon,
+    #[serde(deserialize_with = "Option::deserialize")]
+    pub beam_contour: Option<Vec<LatLon>>,
+}
+
+impl Beam {
+    /// center_observer: an observer on the ground at the beam centre.
+    pub fn center_observer(&self) -> Observer {
+        Observer::from_coordinates([self.beam_center.latitude, self.beam_center.longitude_180(), 0.0])
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HandOverRow {
+    #[serde(deserialize_with = "deserialize_utc")]
+    pub start_time: NaiveDateTime,
+    #[serde(deserialize_with = "deserialize_utc")]
+    pub end_time: NaiveDateTime,
+    pub forward_carrier: Carrier,
+    pub return_carrier: Carrier,
+    pub recurrence_type: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+pub enum Polarization {
+    RHCP,
+    LHCP,
+}
+
+/// A carrier. Frequencies and bandwidth in MHz, powers in dBm.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Carrier {
+    #[serde(rename = "bandwidthMHz")]
+    pub bandwidth_mhz: f64,
+    #[serde(rename = "uplinkCenterFrequencyMHz")]
+    pub uplink_center_frequency_mhz: f64,
+    #[serde(rename = "downlinkCenterFrequencyMHz")]
+    pub downlink_center_frequency_mhz: f64,
+    #[serde(rename = "uplinkPolarization")]
+    pub uplink_polarization: Polarization,
+    #[serde(rename = "downlinkPolarization")]
+    pub downlink_polarization: Polarization,
+    #[serde(rename = "eirpLimitdBm")]
+    pub eirp_limit_dbm: f64,
+    #[serde(rename = "eirpNominaldBm")]
+    pub eirp_nominal_dbm: f64,
+    #[serde(rename = "modemOutputPowerLimitdBm", deserialize_with = "Option::deserialize")]
+    pub modem_output_power_limit_dbm: Option<f64>,
+    #[serde(rename = "modemOutputPowerNominaldBm", deserialize_with = "Option::deserialize")]
+    pub modem_output_power_nominal_dbm: Option<f64>,
+}
+
+impl Carrier {
+    pub fn frequencies(&self) -> CarrierFrequencies {
+        CarrierFrequencies {
+            uplink_mhz: self.uplink_center_frequency_mhz,
+            downlink_mhz: self.downlink_center_frequency_mhz,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LinkBudgetSummary {
+    pub forward_link: LinkSummary,
+    pub return_link: LinkSummary,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LinkSummary {
+    pub modcods: Vec<Modcod>,
+    pub roll_off: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Modcod {
+    pub name: String,
+    #[serde(rename = "esn0dB")]
+    pub esn0_db: f64,
+    #[serde(rename = "spectralEfficiencyBitSymbol")]
+    pub spectral_efficiency_bit_symbol: f64,
+}
+
+impl LinkConfigPlan {
+    /// from_json: parses and checks a link configuration plan.
+    /// Errors name the JSON path of the offending field, e.g. handOverRows.MP01[2].endTime.
+    pub fn from_json(json: &str) -> anyhow::Result<LinkConfigPlan> {
+        let deserializer = &mut serde_json::Deserializer::from_str(json);
+        let plan: LinkConfigPlan = serde_path_to_error::deserialize(deserializer)
+            .map_err(|err| anyhow::anyhow!("invalid link config plan at {}: {}", err.path(), err.inner()))?;
+
+        for (satellite, rows) in &plan.hand_over_rows {
+            for (index, row) in rows.iter().enumerate() {
+                if row.end_time <= row.start_time {
+                    anyhow::bail!("invalid link config plan at handOverRows.{}[{}]: endTime {} is not after startTime {}",
+                                  satellite, index, row.end_time, row.start_time);
+                }
+            }
+        }
+        Ok(plan)
+    }
+
+    /// gateway_observer: the gateway looked up by gatewayID in the registry,
+    /// the gateway beam centre when the id is not known.
+    pub fn gateway_observer(&self, registry: &SiteRegistry) -> Observer {
+        registry.get(&self.gateway_id)
+            .map(|site| site.observer)
+            .unwrap_or_else(|| self.gateway_beam.center_observer())
+    }
+
+    /// terminal_observer: the terminal looked up by terminalID in the registry,
+    /// the terminal beam centre when the id is not known.
+    pub fn terminal_observer(&self, registry: &SiteRegistry) -> Observer {
+        registry.get(&self.terminal_id)
+            .map(|site| site.observer)
+            .unwrap_or_else(|| self.terminal_beam.center_observer())
+    }
+
+    /// handovers: every handover slot as (satellite name, row), sorted by start time.
+    pub fn handovers(&self) -> Vec<(&str, &HandOverRow)> {
+        let mut rows: Vec<(&str, &HandOverRow)> = self.hand_over_rows
+            .iter()
+            .flat_map(|(satellite, rows)| rows.iter().map(move |row| (satellite.as_str(), row)))
+            .collect();
+        rows.sort_by_key(|(_, row)| row.start_time);
+        rows
+    }
+}
+
+/// load_lcp: reads a link configuration plan JSON file.
+pub fn load_lcp(path: &Path) -> anyhow::Result<LinkConfigPlan> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("could not read link config plan {}: {}", path.display(), err))?;
+    LinkConfigPlan::from_json(&json)
+        .map_err(|err| anyhow::anyhow!("{}: {}", path.display(), err))
+}
+
+/// LCP times are RFC 3339 UTC strings ("2024-04-11T01:25:24Z").
+fn deserialize_utc<'de, D>(deserializer: D) -> Result<NaiveDateTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = String::deserialize(deserializer)?;
+    DateTime::parse_from_rfc3339(&text)
+        .map(|time| time.naive_utc())
+        .map_err(|err| serde::de::Error::custom(format!("bad UTC time {:?}: {}", text, err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = include_str!("../../deprecated_src/lcp.json");
+
+    #[test]
+    fn sample_plan_loads() {
+        let plan = LinkConfigPlan::from_json(SAMPLE).unwrap();
+        assert_eq!(plan.gateway_id, "GS_US_PHX");
+        assert!(plan.beam_grid_id.is_none());
+        assert!(plan.gateway_beam.beam_contour.is_none());
+        assert!(!plan.handovers().is_empty());
+        assert!(plan.handovers().windows(2).all(|pair| pair[0].1.start_time <= pair[1].1.start_time));
+        assert!(!plan.link_budget_summary.forward_link.modcods.is_empty());
+    }
+
+    #[test]
+    fn missing_fields_are_named_by_path() {
+        // Nullable fields still have to be there. (JSON pointer of the parent, field, path in the error)
+        for (parent, field, path) in [("", "beamGridID", "invalid link config plan at ."),
+                                      ("/handOverRows/MP01/0/returnCarrier", "modemOutputPowerNominaldBm",
+                                       "at handOverRows.MP01[0].returnCarrier"),
+                                      ("/handOverRows/MP01/0", "endTime", "at handOverRows.MP01[0]")] {
+            let mut json: serde_json::Value = serde_json::from_str(SAMPLE).unwrap();
+            json.pointer_mut(parent).unwrap().as_object_mut().unwrap().remove(field).unwrap();
+            let message = LinkConfigPlan::from_json(&json.to_string()).unwrap_err().to_string();
+            assert!(message.contains(path) && message.contains(&format!("missing field `{}`", field)), "{}", message);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! purpose:
+//!     validate.rs checks an LCP handover schedule against computed geometry.
+//!     Every slot must have its satellite above the elevation mask of both the gateway
+//!     and the terminal from startTime to endTime. Consecutive slots are checked for
+//!     gaps (no satellite scheduled) and overlaps (two satellites scheduled), either makes
+//!     the plan invalid.
+
+use chrono::{NaiveDateTime, TimeDelta};
+use crate::lcp::plan::LinkConfigPlan;
+use crate::obs::observer::Observer;
+use crate::sat::satellite::Satellite;
+use crate::vis::passes::{find_passes, PassConfig, DEFAULT_MIN_ELEVATION};
+
+/// Masks in degrees, step is the elevation sampling interval inside a slot.
+#[derive(Debug, Copy, Clone)]
+pub struct ValidationConfig {
+    pub gateway_mask: f64,
+    pub terminal_mask: f64,
+    pub step: TimeDelta,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> ValidationConfig {
+        ValidationConfig {
+            gateway_mask: DEFAULT_MIN_ELEVATION,
+            terminal_mask: DEFAULT_MIN_ELEVATION,
+            step: TimeDelta::seconds(10),
+        }
+    }
+}
+
+/// Geometry of one slot as seen by one observer.
+/// elevation_margin: lowest elevation in the slot minus the mask and the stale TLE margin the
+/// passes were searched with, degrees.
+/// lead: time the satellite was already visible before the slot started.
+/// lag: time the satellite stays visible after the slot ended.
+/// uncovered: parts of the slot where the satellite is below the mask.
+#[derive(Debug, Clone)]
+pub struct ObserverCheck {
+    pub min_elevation: f64,
+    pub min_elevation_time: NaiveDateTime,
+    pub elevation_margin: f64,
+    pub lead: Option<TimeDelta>,
+    pub lag: Option<TimeDelta>,
+    pub uncovered: Vec<(NaiveDateTime, NaiveDateTime)>,
+}
+
+impl ObserverCheck {
+    pub fn is_valid(&self) -> bool {
+        self.uncovered.is_empty()
+    }
+
+    /// time_margin: the smaller of lead and lag, None if the slot is not fully covered.
+    pub fn time_margin(&self) -> Option<TimeDelta> {
+        match (self.lead, self.lag) {
+            (Some(lead), Some(lag)) if self.is_valid() => Some(lead.min(lag)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SlotCheck {
+    pub satellite: String,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub gateway: ObserverCheck,
+    pub terminal: ObserverCheck,
+}
+
+impl SlotCheck {
+    pub fn is_valid(&self) -> bool {
+        self.gateway.is_valid() && self.terminal.is_valid()
+    }
+}
+
+/// Hand over from one slot to the next in time order.
+/// gap is next start minus previous end: positive is a gap, negative an overlap.
+#[derive(Debug, Clone)]
+pub struct Transition {
+    pub from_satellite: String,
+    pub to_satellite: String,
+    pub from_end: NaiveDateTime,
+    pub to_start: NaiveDateTime,
+    pub gap: TimeDelta,
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidationReport {
+    pub slots: Vec<SlotCheck>,
+    pub transitions: Vec<Transition>,
+    /// Satellites named in the plan that are not in the satellite set.
+    pub missing_satellites: Vec<String>,
+}
+
+impl ValidationReport {
+    /// violations: slots where the satellite dips below a mask.
+    pub fn violations(&self) -> Vec<&SlotCheck> {
+        self.slots.iter().filter(|slot| !slot.is_valid()).collect()
+    }
+
+    pub fn gaps(&self) -> Vec<&Transition> {
+        self.transitions.iter().filter(|t| t.gap > TimeDelta::zero()).collect()
+    }
+
+    pub fn overlaps(&self) -> Vec<&Transition> {
+        self.transitions.iter().filter(|t| t.gap < TimeDelta::zero()).collect()
+    }
+
+    /// is_valid: every satellite is known, every slot covered and slots follow each other without gap or overlap.
+    pub fn is_valid(&self) -> bool {
+        self.missing_satellites.is_empty() && self.violations().is_empty()
+            && self.gaps().is_empty() && self.overlaps().is_empty()
+    }
+}
+
+/// validate_plan: checks every handover slot of a plan.
+/// input: plan, satellites (matched on the 3LE name), gateway, terminal, config
+/// output: report with one SlotCheck per slot, in time order
+pub fn validate_plan(plan: &LinkConfigPlan,
+                     satellites: &mut [Satellite],
+                     gateway: &Observer,
+                     terminal: &Observer,
+                     config: &ValidationConfig) -> anyhow::Result<ValidationReport> {
+    let handovers = plan.handovers();
+    let mut slots = Vec::new();
+    let mut missing_satellites: Vec<String> = Vec::new();
+
+    for (name, row) in &handovers {
+        let satellite = match satellites.iter_mut()
+            .find(|sat| sat.name() == *name) {
+            Some(satellite) => satellite,
+            None => {
+                if !missing_satellites.iter().any(|missing| missing == name) {
+                    missing_satellites.push(name.to_string());
+                }
+                continue;
+            }
+        };
+
+        slots.push(SlotCheck {
+            satellite: name.to_string(),
+            start: row.start_time,
+            end: row.end_time,
+            gateway: check_observer(gateway, satellite, &row.start_time, &row.end_time, config.gateway_mask, &config.step)?,
+            terminal: check_observer(terminal, satellite, &row.start_time, &row.end_time, config.terminal_mask, &config.step)?,
+        });
+    }
+
+    let transitions = handovers
+        .windows(2)
+        .map(|pair| Transition {
+            from_satellite: pair[0].0.to_string(),
+            to_satellite: pair[1].0.to_string(),
+            from_end: pair[0].1.end_time,
+            to_start: pair[1].1.start_time,
+            gap: pair[1].1.start_time - pair[0].1.end_time,
+        })
+        .collect();
+
+    Ok(ValidationReport {
+        slots,
+        transitions,
+        missing_satellites,
+    })
+}
+
+fn check_observer(observer: &Observer,
+                  satellite: &mut Satellite,
+                  start: &NaiveDateTime,
+                  end: &NaiveDateTime,
+                  mask: f64,
+                  step: &TimeDelta) -> anyhow::Result<ObserverCheck> {
+    // Search one orbit either side of the slot so the passes covering its ends are whole,
+    // as far as an ephemeris reaches.
+    let period = satellite.orbital_period()?;
+    let (mut search_start, mut search_end) = (*start - period, *end + period);
+    if let Some((first, last)) = satellite.valid_span() {
+        search_start = search_start.max(first).min(*start);
+        search_end = search_end.min(last).max(*end);
+    }
+    // find_passes raises the mask by the staleness margin over the whole search, report against the same.
+    let stale_margin = satellite.elevation_margin(&search_start, &search_end);
+    // The mask given for the check replaces the site's own.
+    let masked = Observer { elevation_mask: mask, ..*observer };
+    let pass_config = PassConfig {
+        min_elevation: mask,
+        ..Default::default()
+    };
+    let passes = find_passes(&masked, satellite, &search_start, &search_end, &pass_config)?;
+
+    let lead = passes.iter()
+        .find(|pass| pass.aos <= *start && pass.los >= *start)
+        .map(|pass| *start - pass.aos);
+    let lag = passes.iter()
+        .find(|pass| pass.aos <= *end && pass.los >= *end)
+        .map(|pass| pass.los - *end);
+
+    // Walk the slot and collect the parts not inside any pass.
+    let mut uncovered = Vec::new();
+    let mut cursor = *start;
+    for pass in passes.iter().filter(|pass| pass.los > *start && pass.aos < *end) {
+        if pass.aos > cursor {
+            uncovered.push((cursor, pass.aos));
+        }
+        cursor = cursor.max(pass.los);
+    }
+    if cursor < *end {
+        uncovered.push((cursor, *end));
+    }
+
+    let mut observer = *observer;
+    let mut min_elevation = f64::INFINITY;
+    let mut min_elevation_time = *start;
+    let mut t = *start;
+    loop {
+        satellite.propagate(&t)?;
+        let elevation = observer.look_angle_to(satellite).elevation;
+        if elevation < min_elevation {
+            min_elevation = elevation;
+            min_elevation_time = t;
+        }
+        if t >= *end {
+            break;
+        }
+        t = (t + *step).min(*end);
+    }
+
+    Ok(ObserverCheck {
+        min_elevation,
+        min_elevation_time,
+        elevation_margin: min_elevation - mask - stale_margin,
+        lead,
+        lag,
+        uncovered,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::sat::tle::parse_tles;
+
+    /// The sample plan with its handover rows replaced by slots of UK-DMC 2.
+    fn plan(slots: &[(NaiveDateTime, NaiveDateTime)]) -> LinkConfigPlan {
+        let mut json: serde_json::Value = serde_json::from_str(include_str!("../../deprecated_src/lcp.json")).unwrap();
+        let template = json["handOverRows"]["MP01"][0].clone();
+        let rows: Vec<serde_json::Value> = slots.iter()
+            .map(|(start, end)| {
+                let mut row = template.clone();
+                row["startTime"] = start.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string().into();
+                row["endTime"] = end.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string().into();
+                row
+            })
+            .collect();
+        json["handOverRows"] = serde_json::json!({ "UK-DMC 2": rows });
+        LinkConfigPlan::from_json(&json.to_string()).unwrap()
+    }
+
+    fn setup() -> (Vec<Satellite>, Observer, Vec<(NaiveDateTime, NaiveDateTime)>) {
+        let mut satellites = vec![Satellite::new(parse_tles(include_str!("../../common/tle2.txt")).elements.remove(0))];
+        let phoenix = Observer::from_coordinates([33.4484, -112.0740, 0.331]);
+        let start = NaiveDate::from_ymd_opt(2012, 10, 15).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let passes = find_passes(&phoenix, &mut satellites[0], &start, &(start + TimeDelta::days(1)), &PassConfig::default())
+            .unwrap()
+            .iter()
+            .map(|pass| (pass.aos, pass.los))
+            .collect();
+        (satellites, phoenix, passes)
+    }
+
+    #[test]
+    fn slot_inside_a_pass_is_covered() {
+        let (mut satellites, phoenix, passes) = setup();
+        let (aos, los) = passes[0];
+        let slot = (aos + TimeDelta::seconds(5), los - TimeDelta::seconds(20));
+        let report = validate_plan(&plan(&[slot]), &mut satellites, &phoenix, &phoenix, &ValidationConfig::default()).unwrap();
+
+        assert!(report.is_valid());
+        let check = &report.slots[0].gateway;
+        assert!(check.uncovered.is_empty());
+        assert!(check.elevation_margin >= 0.0, "{}", check.elevation_margin);
+        assert!((check.lead.unwrap() - TimeDelta::seconds(5)).abs() <= TimeDelta::milliseconds(2), "{:?}", check.lead);
+        assert!((check.lag.unwrap() - TimeDelta::seconds(20)).abs() <= TimeDelta::milliseconds(2), "{:?}", check.lag);
+        assert_eq!(check.time_margin(), check.lead);
+    }
+
+    #[test]
+    fn handover_past_los_is_reported() {
+        let (mut satellites, phoenix, passes) = setup();
+        let (aos, los) = passes[0];
+        // The first slot runs a minute past LOS, the second leaves a gap before it.
+        let slots = [(aos + TimeDelta::seconds(5), los + TimeDelta::seconds(60)),
+                     (passes[1].0 + TimeDelta::seconds(5), passes[1].1 - TimeDelta::seconds(5))];
+        let report = validate_plan(&plan(&slots), &mut satellites, &phoenix, &phoenix, &ValidationConfig::default()).unwrap();
+
+        assert!(!report.is_valid());
+        assert_eq!(report.violations().len(), 1);
+        let uncovered = &report.slots[0].terminal.uncovered;
+        assert_eq!(uncovered.len(), 1);
+        assert!((uncovered[0].0 - los).abs() <= TimeDelta::milliseconds(2), "{:?}", uncovered);
+        assert_eq!(uncovered[0].1, report.slots[0].end);
+        assert!(report.slots[0].terminal.elevation_margin < 0.0);
+        assert!(report.slots[1].is_valid());
+        assert_eq!(report.gaps().len(), 1);
+        assert_eq!(report.gaps()[0].gap, report.slots[1].start - report.slots[0].end);
+    }
+}
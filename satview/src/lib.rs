@@ -0,0 +1,10 @@
+//! satview: windows of time and contours for when a satellite is visible
+//! based on observer location and satellite location.
+
+pub mod utils; pub use utils::coordinate_systems;
+pub mod obs;
+pub mod sat;
+pub mod vis;
+pub mod link;
+pub mod lcp;
+pub mod render;
@@ -0,0 +1,151 @@
+//! purpose:
+//!     doppler.rs predicts the Doppler offset and Doppler rate seen by an observer
+//!     for a carrier, and writes them out as a pre-compensation table.
+//!     Positive offsets (blue shift) while the satellite approaches, negative (red shift)
+//!     while it recedes.
+
+use std::io::Write;
+use chrono::{NaiveDateTime, TimeDelta};
+use crate::coordinate_systems::LookAngle;
+use crate::link::path_loss::SPEED_OF_LIGHT;
+use crate::obs::observer::Observer;
+use crate::sat::satellite::Satellite;
+use crate::sat::staleness::days;
+
+/// Half width of the central difference used for the Doppler rate, in seconds.
+const RATE_HALF_WIDTH: f64 = 0.5;
+
+/// Doppler of a carrier at one instant.
+/// elevation in degrees, range_rate in km/s, offset in Hz, rate in Hz/s.
+/// tle_age is the time from the TLE epoch the sample was predicted with, None without a TLE.
+#[derive(Debug, Copy, Clone)]
+pub struct DopplerSample {
+    pub time: NaiveDateTime,
+    pub elevation: f64,
+    pub range_rate: f64,
+    pub offset: f64,
+    pub rate: f64,
+    pub tle_age: Option<TimeDelta>,
+}
+
+/// doppler_offset: frequency offset of a carrier for a given range rate.
+/// input: range rate in km/s (positive receding), carrier frequency in MHz
+/// output: offset in Hz
+pub fn doppler_offset(range_rate: &f64, frequency_mhz: &f64) -> f64 {
+    -frequency_mhz * 1e6 * range_rate / SPEED_OF_LIGHT
+}
+
+/// doppler_series: Doppler offset and rate sampled every step from start to end.
+/// input: observer, satellite, start and end (UTC), step, carrier frequency in MHz
+/// output: samples in chronological order, the last sample is at end
+pub fn doppler_series(observer: &Observer,
+                      satellite: &mut Satellite,
+                      start: &NaiveDateTime,
+                      end: &NaiveDateTime,
+                      step: &TimeDelta,
+                      frequency_mhz: &f64) -> anyhow::Result<Vec<DopplerSample>> {
+    if end < start {
+        anyhow::bail!("doppler range end {} is before start {}", end, start);
+    }
+    if *step <= TimeDelta::zero() {
+        anyhow::bail!("doppler step must be positive");
+    }
+
+    let mut observer = *observer;
+    let half_width = TimeDelta::nanoseconds((RATE_HALF_WIDTH * 1e9) as i64);
+    let mut look_angle_at = |t: &NaiveDateTime| -> anyhow::Result<(LookAngle, Option<TimeDelta>)> {
+        let tle_age = satellite.propagate(t)?.tle_age;
+        Ok((observer.look_angle_to(satellite), tle_age))
+    };
+
+    let mut samples = Vec::new();
+    let mut t = *start;
+    loop {
+        let before = look_angle_at(&(t - half_width))?.0.range_rate;
+        let after = look_angle_at(&(t + half_width))?.0.range_rate;
+        let (look, tle_age) = look_angle_at(&t)?;
+        let range_acceleration = (after - before) / (2.0 * RATE_HALF_WIDTH);
+
+        samples.push(DopplerSample {
+            time: t,
+            elevation: look.elevation,
+            range_rate: look.range_rate,
+            offset: doppler_offset(&look.range_rate, frequency_mhz),
+            rate: doppler_offset(&range_acceleration, frequency_mhz),
+            tle_age,
+        });
+
+        if t >= *end {
+            break;
+        }
+        t = (t + *step).min(*end);
+    }
+    Ok(samples)
+}
+
+/// write_doppler_table: writes samples as CSV, one row per sample.
+/// Columns: time (UTC, ISO 8601), seconds since the first sample, elevation (deg),
+/// range rate (km/s), Doppler offset (Hz), Doppler rate (Hz/s), TLE age (days, empty without a TLE).
+pub fn write_doppler_table<W: Write>(writer: &mut W, samples: &[DopplerSample]) -> anyhow::Result<()> {
+    writeln!(writer, "time_utc,elapsed_s,elevation_deg,range_rate_km_s,doppler_hz,doppler_rate_hz_s,tle_age_days")?;
+    let first = match samples.first() {
+        Some(sample) => sample.time,
+        None => return Ok(()),
+    };
+    for sample in samples {
+        let elapsed = (sample.time - first).num_milliseconds() as f64 / 1000.0;
+        let tle_age = sample.tle_age.map_or(String::new(), |age| format!("{:.6}", days(&age)));
+        writeln!(writer, "{},{:.3},{:.4},{:.6},{:.3},{:.4},{}",
+                 sample.time.format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+                 elapsed,
+                 sample.elevation,
+                 sample.range_rate,
+                 sample.offset,
+                 sample.rate,
+                 tle_age)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::sat::tle::parse_tles;
+    use crate::vis::passes::{find_passes, PassConfig};
+
+    #[test]
+    fn approaching_pass_is_blue_shifted() {
+        let mut satellite = Satellite::new(parse_tles(include_str!("../../common/tle2.txt")).elements.remove(0));
+        let observer = Observer::from_coordinates([33.4484, -112.0740, 0.331]);
+        let start = NaiveDate::from_ymd_opt(2012, 10, 15).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let pass = find_passes(&observer, &mut satellite, &start, &(start + TimeDelta::days(1)), &PassConfig::default())
+            .unwrap()[0];
+        let samples = doppler_series(&observer, &mut satellite, &pass.aos, &pass.los, &TimeDelta::seconds(5), &2200.0).unwrap();
+
+        let (first, last) = (samples[0], samples[samples.len() - 1]);
+        assert!(first.range_rate < 0.0 && first.offset > 0.0, "{:?}", first);
+        assert!(last.range_rate > 0.0 && last.offset < 0.0, "{:?}", last);
+        // LEO at S band: tens of kHz, falling fastest at culmination.
+        assert!(first.offset > 20_000.0 && first.offset < 60_000.0, "{} Hz", first.offset);
+        assert!(samples.iter().all(|sample| sample.rate < 0.0));
+        for pair in samples.windows(2) {
+            let elapsed = (pair[1].time - pair[0].time).num_milliseconds() as f64 / 1000.0;
+            let slope = (pair[1].offset - pair[0].offset) / elapsed;
+            assert!((slope - 0.5 * (pair[0].rate + pair[1].rate)).abs() < 0.05 * slope.abs() + 1.0,
+                    "{} Hz/s vs {:?}", slope, pair);
+        }
+        assert!((doppler_offset(&-1.0, &2200.0) - 2200e6 / SPEED_OF_LIGHT).abs() < 1e-9);
+
+        // Every row carries the TLE age it was predicted with.
+        assert!(samples.iter().all(|sample| sample.tle_age == satellite.tle_age_at(&sample.time)));
+        let mut table = Vec::new();
+        write_doppler_table(&mut table, &samples[..2]).unwrap();
+        let table = String::from_utf8(table).unwrap();
+        let rows: Vec<&str> = table.lines().collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].ends_with(",tle_age_days"), "{}", rows[0]);
+        let age: f64 = rows[1].rsplit(',').next().unwrap().parse().unwrap();
+        assert!((age - days(&samples[0].tle_age.unwrap())).abs() < 1e-6, "{}", rows[1]);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod path_loss;
+pub mod doppler;
\ No newline at end of file
@@ -0,0 +1,123 @@
+//! purpose:
+//!     path_loss.rs computes free-space path loss between an observer and a satellite
+//!     from the slant range and the carrier centre frequencies of a link-config plan.
+
+use chrono::{NaiveDateTime, TimeDelta};
+use crate::obs::observer::Observer;
+use crate::sat::satellite::Satellite;
+use crate::vis::passes::PassWindow;
+
+/// Speed of light in km/s.
+pub const SPEED_OF_LIGHT: f64 = 299_792.458;
+
+/// Centre frequencies of a carrier in MHz
+/// (uplinkCenterFrequencyMHz / downlinkCenterFrequencyMHz in a link-config plan).
+#[derive(Debug, Copy, Clone)]
+pub struct CarrierFrequencies {
+    pub uplink_mhz: f64,
+    pub downlink_mhz: f64,
+}
+
+/// Path loss of both legs of a carrier at one instant.
+/// elevation in degrees, slant_range in km, losses in dB.
+/// tle_age is the time from the TLE epoch the sample was predicted with, None without a TLE.
+#[derive(Debug, Copy, Clone)]
+pub struct PathLossSample {
+    pub time: NaiveDateTime,
+    pub elevation: f64,
+    pub slant_range: f64,
+    pub uplink_loss: f64,
+    pub downlink_loss: f64,
+    pub tle_age: Option<TimeDelta>,
+}
+
+/// free_space_path_loss: FSPL = 20 log10(4 pi d f / c)
+/// input: slant range in km, frequency in MHz
+/// output: path loss in dB
+pub fn free_space_path_loss(slant_range: &f64, frequency_mhz: &f64) -> f64 {
+    let wavelength = SPEED_OF_LIGHT / (frequency_mhz * 1e6); // km
+    20.0 * (4.0 * core::f64::consts::PI * slant_range / wavelength).log10()
+}
+
+/// path_loss_series: uplink and downlink path loss sampled every step from start to end.
+/// input: observer, satellite, start and end (UTC), step, carrier frequencies
+/// output: samples in chronological order, the last sample is at end
+pub fn path_loss_series(observer: &Observer,
+                        satellite: &mut Satellite,
+                        start: &NaiveDateTime,
+                        end: &NaiveDateTime,
+                        step: &TimeDelta,
+                        carrier: &CarrierFrequencies) -> anyhow::Result<Vec<PathLossSample>> {
+    if end < start {
+        anyhow::bail!("path loss range end {} is before start {}", end, start);
+    }
+    if *step <= TimeDelta::zero() {
+        anyhow::bail!("path loss step must be positive");
+    }
+
+    let mut observer = *observer;
+    let mut samples = Vec::new();
+    let mut t = *start;
+    loop {
+        let tle_age = satellite.propagate(&t)?.tle_age;
+        let look = observer.look_angle_to(satellite);
+        samples.push(PathLossSample {
+            time: t,
+            elevation: look.elevation,
+            slant_range: look.distance,
+            uplink_loss: free_space_path_loss(&look.distance, &carrier.uplink_mhz),
+            downlink_loss: free_space_path_loss(&look.distance, &carrier.downlink_mhz),
+            tle_age,
+        });
+
+        if t >= *end {
+            break;
+        }
+        t = (t + *step).min(*end);
+    }
+    Ok(samples)
+}
+
+/// pass_path_loss: path_loss_series from AOS to LOS of a pass.
+pub fn pass_path_loss(observer: &Observer,
+                      satellite: &mut Satellite,
+                      window: &PassWindow,
+                      step: &TimeDelta,
+                      carrier: &CarrierFrequencies) -> anyhow::Result<Vec<PathLossSample>> {
+    path_loss_series(observer, satellite, &window.aos, &window.los, step, carrier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::sat::tle::parse_tles;
+
+    #[test]
+    fn fspl_at_known_range_and_frequency() {
+        // 20 log10(d km) + 20 log10(f MHz) + 32.4478
+        assert!((free_space_path_loss(&1000.0, &2000.0) - 158.4684).abs() < 1e-3);
+        assert!((free_space_path_loss(&35_786.0, &12_000.0) - 205.1057).abs() < 1e-3);
+        // Doubling the range or the frequency adds 6.02 dB.
+        assert!((free_space_path_loss(&2000.0, &2000.0) - free_space_path_loss(&1000.0, &2000.0) - 6.0206).abs() < 1e-4);
+    }
+
+    #[test]
+    fn series_follows_the_slant_range() {
+        let mut satellite = Satellite::new(parse_tles(include_str!("../../common/tle2.txt")).elements.remove(0));
+        let observer = Observer::from_coordinates([33.4484, -112.0740, 0.331]);
+        let start = NaiveDate::from_ymd_opt(2012, 10, 15).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let end = start + TimeDelta::seconds(95);
+        let carrier = CarrierFrequencies { uplink_mhz: 29_896.012, downlink_mhz: 17_734.852 };
+        let samples = path_loss_series(&observer, &mut satellite, &start, &end, &TimeDelta::seconds(10), &carrier).unwrap();
+
+        assert_eq!(samples.len(), 11);
+        assert_eq!(samples.last().unwrap().time, end);
+        for sample in &samples {
+            assert_eq!(sample.tle_age, satellite.tle_age_at(&sample.time));
+            assert!((sample.uplink_loss - free_space_path_loss(&sample.slant_range, &carrier.uplink_mhz)).abs() < 1e-9);
+            assert!((sample.uplink_loss - sample.downlink_loss - 20.0 * (29_896.012f64 / 17_734.852).log10()).abs() < 1e-9);
+        }
+        assert!(path_loss_series(&observer, &mut satellite, &end, &start, &TimeDelta::seconds(10), &carrier).is_err());
+    }
+}
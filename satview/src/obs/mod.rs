@@ -1 +1,3 @@
-pub mod observer;
\ No newline at end of file
+pub mod observer;
+pub mod site;
+pub mod observer_locations;
\ No newline at end of file
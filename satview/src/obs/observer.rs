@@ -1,34 +1,196 @@
-//! purpose: 
+//! purpose:
 //!     observer.rs defines an observer.
 //!     What is an observer? For now an observer is any terrestrial body
 //!     which is "observing" an orbiting body. In our case these orbiting bodies are satellites
-//!     and the terrestrial bodies are "gateways" and "terminals". 
+//!     and the terrestrial bodies are "gateways" and "terminals".
 //!     For purposes of this code the differences between gateways and terminals are not
 //!     important, just know that they are different.
 
-use sgp4::Geopotential;
-use crate::coordinate_systems::Geodetic;
+use chrono::NaiveDateTime;
+use crate::coordinate_systems::{Geodetic, LookAngle, TEME};
+use crate::link::path_loss::free_space_path_loss;
+use crate::sat::satellite::Satellite;
+use crate::vis::passes::DEFAULT_MIN_ELEVATION;
+use crate::utils::satutils::{degrees_to_radians, get_teme, radians_to_degrees, to_local_sidereal_time};
 
 /// An observer has:
-/// geodetic_coordinates
-/// look_angle to satellite
+/// geodetic_coordinates (degrees, km)
+/// teme_coordinates at the last update_state epoch
+/// look_angle to the last satellite looked at
 /// path_loss in dB
+/// elevation_mask in degrees, the lowest elevation the observer can track at
 
 #[derive(Copy, Clone)]
-pub struct Observer { 
+pub struct Observer {
     pub geodetic_coordinates: Geodetic,
-    pub look_angle: f64,
+    pub teme_coordinates: TEME,
+    pub look_angle: LookAngle,
     pub path_loss: f64,
+    pub elevation_mask: f64,
+}
+
+impl Default for Observer {
+    fn default() -> Observer {
+        Observer::new()
+    }
 }
 
 impl Observer {
-    pub fn new() -> Observer { 
+    pub fn new() -> Observer {
         Observer {
-            geodetic_coordinates: Geodetic { 
+            geodetic_coordinates: Geodetic {
+                ..Default::default()
+            },
+            teme_coordinates: TEME {
+                ..Default::default()
+            },
+            look_angle: LookAngle {
                 ..Default::default()
             },
-            look_angle: 0.0,
             path_loss : 0.0,
+            elevation_mask: DEFAULT_MIN_ELEVATION,
+        }
+    }
+
+    /// from_coordinates: creates an observer at [latitude, longitude, altitude].
+    /// input: latitude and longitude in degrees, altitude in km
+    /// output: new observer
+    pub fn from_coordinates(coordinates: [f64; 3]) -> Observer {
+        Observer {
+            geodetic_coordinates: Geodetic {
+                latitude: coordinates[0],
+                longitude: coordinates[1],
+                altitude: coordinates[2],
+            },
+            ..Observer::new()
         }
     }
-}
\ No newline at end of file
+
+    /// update_state: moves the observer's TEME coordinates to the new epoch.
+    pub fn update_state(&mut self, new_epoch: &NaiveDateTime) {
+        self.teme_coordinates = get_teme(&self.geodetic_coordinates, new_epoch);
+    }
+
+    /// calculate_look_angle: sets and returns the look angle to a satellite.
+    /// input: satellite TEME state, epoch of the state
+    /// output: look angle (degrees, km, km/s)
+    pub fn calculate_look_angle(&mut self, sat_coords: &TEME, dt: &NaiveDateTime) -> LookAngle {
+        self.update_state(dt);
+        let rad_long = degrees_to_radians(&self.geodetic_coordinates.longitude);
+        let rad_lat = degrees_to_radians(&self.geodetic_coordinates.latitude);
+        let theta = to_local_sidereal_time(rad_long, &self.teme_coordinates.sidereal);
+
+        let obs = &self.teme_coordinates;
+        let distance_vector: [f64; 3] = [sat_coords.pos_vector[0] - obs.pos_vector[0],
+                                         sat_coords.pos_vector[1] - obs.pos_vector[1],
+                                         sat_coords.pos_vector[2] - obs.pos_vector[2]];
+        let velocity_vector: [f64; 3] = [sat_coords.velo_vector[0] - obs.velo_vector[0],
+                                         sat_coords.velo_vector[1] - obs.velo_vector[1],
+                                         sat_coords.velo_vector[2] - obs.velo_vector[2]];
+
+        let distance = (distance_vector[0].powf(2.0) + distance_vector[1].powf(2.0) + distance_vector[2].powf(2.0)).sqrt();
+        let range_rate = (distance_vector[0] * velocity_vector[0]
+                        + distance_vector[1] * velocity_vector[1]
+                        + distance_vector[2] * velocity_vector[2]) / distance;
+
+        let sin_lat = rad_lat.sin();
+        let cos_lat = rad_lat.cos();
+        let sin_theta = theta.sin();
+        let cos_theta = theta.cos();
+
+        let top_s = sin_lat * cos_theta * distance_vector[0] + sin_lat * sin_theta * distance_vector[1] - cos_lat * distance_vector[2];
+        let top_e = -sin_theta * distance_vector[0] + cos_theta * distance_vector[1];
+        let top_z = cos_lat * cos_theta * distance_vector[0] + cos_lat * sin_theta * distance_vector[1] + sin_lat * distance_vector[2];
+
+        // Azimuth measured clockwise from north, north = -S.
+        let mut az = top_e.atan2(-top_s);
+        if az < 0.0 {
+            az += core::f64::consts::PI * 2.0;
+        }
+
+        let el = (top_z / distance).asin();
+
+        self.look_angle = LookAngle {
+            azimuth: radians_to_degrees(&az),
+            elevation: radians_to_degrees(&el),
+            distance,
+            range_rate,
+        };
+        self.look_angle
+    }
+
+    /// look_angle_to: azimuth, elevation, slant range and range rate to a satellite
+    /// at the epoch the satellite was last propagated to.
+    /// input: satellite
+    /// output: look angle (degrees, km, km/s)
+    pub fn look_angle_to(&mut self, satellite: &Satellite) -> LookAngle {
+        self.calculate_look_angle(&satellite.teme_coordinates, &satellite.epoch)
+    }
+
+    /// calculate_path_loss: sets and returns the free-space path loss over the
+    /// current look angle distance.
+    /// input: carrier frequency in MHz
+    /// output: path loss in dB
+    pub fn calculate_path_loss(&mut self, frequency_mhz: &f64) -> f64 {
+        self.path_loss = free_space_path_loss(&self.look_angle.distance, frequency_mhz);
+        self.path_loss
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeDelta};
+    use crate::coordinate_systems::AER;
+    use crate::sat::tle::parse_tles;
+    use crate::utils::satutils::{aer_to_enu, ecef_to_enu, ecef_to_teme, enu_to_aer, enu_to_ecef, get_ecef};
+
+    #[test]
+    fn look_angle_matches_the_local_frame() {
+        // The Colorado Springs site and look angle of the satutils AER round trip.
+        let site = Geodetic { latitude: 39.007, longitude: -104.883, altitude: 2.19456 };
+        let aer = AER { azimuth: 205.6, elevation: 30.7, range: 604.68 };
+        let epoch = NaiveDate::from_ymd_opt(1994, 5, 14).unwrap().and_hms_opt(13, 11, 20).unwrap();
+        let target = enu_to_ecef(&aer_to_enu(&aer), &site);
+        let mut observer = Observer::from_coordinates([site.latitude, site.longitude, site.altitude]);
+
+        // A point fixed to the earth keeps its distance.
+        let look = observer.calculate_look_angle(&ecef_to_teme(&target, &[0.0, 0.0, 0.0], &epoch), &epoch);
+        assert!((look.azimuth - 205.6).abs() < 1e-9, "{:?}", look);
+        assert!((look.elevation - 30.7).abs() < 1e-9, "{:?}", look);
+        assert!((look.distance - 604.68).abs() < 1e-9, "{:?}", look);
+        assert!(look.range_rate.abs() < 1e-12, "{:?}", look);
+        let local = enu_to_aer(&ecef_to_enu(&target, &site));
+        assert!((look.azimuth - local.azimuth).abs() < 1e-9 && (look.elevation - local.elevation).abs() < 1e-9);
+
+        // Moving straight at the observer at 2 km/s, then straight away.
+        let origin = get_ecef(&site);
+        let line_of_sight = [(target.x - origin.x) / aer.range, (target.y - origin.y) / aer.range, (target.z - origin.z) / aer.range];
+        for speed in [-2.0, 2.0] {
+            let velocity = line_of_sight.map(|component| component * speed);
+            let look = observer.calculate_look_angle(&ecef_to_teme(&target, &velocity, &epoch), &epoch);
+            assert!((look.range_rate - speed).abs() < 1e-9, "{:?}", look);
+        }
+    }
+
+    #[test]
+    fn range_rate_is_the_change_of_distance() {
+        let mut satellite = Satellite::new(parse_tles(include_str!("../../common/tle2.txt")).elements.remove(0));
+        let mut observer = Observer::from_coordinates([33.4484, -112.0740, 0.331]);
+        // Rising in the north east and setting in the south, the first pass over Phoenix on 2012-10-15.
+        let rising = NaiveDate::from_ymd_opt(2012, 10, 15).unwrap().and_hms_opt(6, 11, 0).unwrap();
+        for (time, approaching) in [(rising, true), (rising + TimeDelta::minutes(5), false)] {
+            let mut distance_at = |time: NaiveDateTime| {
+                satellite.propagate(&time).unwrap();
+                observer.look_angle_to(&satellite).distance
+            };
+            // Distance change over one second, km/s.
+            let slope = distance_at(time + TimeDelta::milliseconds(500)) - distance_at(time - TimeDelta::milliseconds(500));
+            satellite.propagate(&time).unwrap();
+            let look = observer.look_angle_to(&satellite);
+            assert!(look.elevation > 0.0, "{:?}", look);
+            assert_eq!(look.range_rate < 0.0, approaching, "{:?}", look);
+            assert!((look.range_rate - slope).abs() < 1e-3, "{} vs {} km/s", look.range_rate, slope);
+        }
+    }
+}
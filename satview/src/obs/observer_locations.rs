@@ -0,0 +1,106 @@
+//! purpose:
+//!     observer_locations.rs is the registry of known sites, keyed by the id used in link
+//!     config plans (gatewayID, e.g. GS_US_PHX). It starts with the built-in gateways and can
+//!     be extended, or overridden entry by entry, from a user site file (see site.rs).
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use crate::obs::observer::Observer;
+use crate::obs::site::{load_sites, Antenna, Site, SiteRole, DEFAULT_SITE_COLOR};
+
+/// Built-in gateways as (id, latitude, longitude, altitude in km), degrees.
+/// Masks default to DEFAULT_MIN_ELEVATION, a user site file can override them.
+pub const BUILT_IN_GATEWAYS: [(&str, f64, f64, f64); 1] = [
+    ("GS_US_PHX", 33.4484, -112.0740, 0.331), // Phoenix, Arizona
+];
+
+/// Known sites by id.
+#[derive(Clone)]
+pub struct SiteRegistry {
+    sites: BTreeMap<String, Site>,
+}
+
+impl Default for SiteRegistry {
+    fn default() -> SiteRegistry {
+        SiteRegistry::built_in()
+    }
+}
+
+impl SiteRegistry {
+    /// built_in: registry holding only the built-in gateways.
+    pub fn built_in() -> SiteRegistry {
+        let sites = BUILT_IN_GATEWAYS.iter()
+            .map(|(id, latitude, longitude, altitude)| {
+                let site = Site {
+                    id: id.to_string(),
+                    role: SiteRole::Gateway,
+                    observer: Observer::from_coordinates([*latitude, *longitude, *altitude]),
+                    antenna: Antenna::default(),
+                    color: DEFAULT_SITE_COLOR,
+                };
+                (id.to_string(), site)
+            })
+            .collect();
+        SiteRegistry { sites }
+    }
+
+    /// extend: adds sites, replacing known sites with the same id.
+    pub fn extend(&mut self, sites: Vec<Site>) {
+        for site in sites {
+            self.sites.insert(site.id.clone(), site);
+        }
+    }
+
+    /// extend_from_file: adds the sites of a user site file, replacing known sites with the same id.
+    pub fn extend_from_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.extend(load_sites(path)?);
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Site> {
+        self.sites.get(id)
+    }
+
+    /// site: a known site, the error lists the known ids.
+    pub fn site(&self, id: &str) -> anyhow::Result<&Site> {
+        self.get(id)
+            .ok_or_else(|| anyhow::anyhow!("unknown site {}, known sites are {}", id, self.ids().join(", ")))
+    }
+
+    /// observer: the observer of a known site, with its elevation mask.
+    pub fn observer(&self, id: &str) -> anyhow::Result<Observer> {
+        Ok(self.site(id)?.observer)
+    }
+
+    /// ids: every known id in alphabetical order.
+    pub fn ids(&self) -> Vec<&str> {
+        self.sites.keys().map(|id| id.as_str()).collect()
+    }
+
+    /// sites: every known site in id order.
+    pub fn sites(&self) -> impl Iterator<Item = &Site> {
+        self.sites.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vis::passes::DEFAULT_MIN_ELEVATION;
+
+    #[test]
+    fn site_file_overrides_built_in_gateways_by_id() {
+        let mut registry = SiteRegistry::built_in();
+        let phoenix = registry.observer("GS_US_PHX").unwrap();
+        assert_eq!(phoenix.elevation_mask, DEFAULT_MIN_ELEVATION);
+        assert!(registry.site("UT_FR_01").is_err());
+
+        registry.extend(Site::sites_from_json(include_str!("../../common/sites.json")).unwrap());
+        assert_eq!(registry.ids(), ["GS_US_PHX", "UT_FR_01"]);
+        assert_eq!(registry.observer("GS_US_PHX").unwrap().elevation_mask, 10.0);
+        assert_eq!(registry.site("UT_FR_01").unwrap().role, SiteRole::Terminal);
+
+        let unknown = registry.site("GS_XX").err().unwrap().to_string();
+        assert_eq!(unknown, "unknown site GS_XX, known sites are GS_US_PHX, UT_FR_01");
+    }
+}
@@ -0,0 +1,214 @@
+//! purpose:
+//!     site.rs defines the site file, a JSON list of the gateways and terminals to observe from,
+//!     and loads it into Observer values. A site file looks like
+//!     {"sites": [{"id": "GS_US_PHX", "role": "gateway", "latitude": 33.4484, "longitude": -112.0740,
+//!                 "altitudeKm": 0.331, "elevationMaskDeg": 10, "color": "#ff0000",
+//!                 "antenna": {"diameterM": 7.3, "gainDbi": 62.1}}]}
+//!     Field names are camelCase with the unit as suffix, unknown fields are rejected.
+//!     altitudeKm defaults to 0, elevationMaskDeg to 15 and color to red.
+
+use std::path::Path;
+use serde::{Deserialize, Deserializer};
+use crate::obs::observer::Observer;
+use crate::vis::passes::DEFAULT_MIN_ELEVATION;
+
+/// Colour used when a site has none, red 0% transparent.
+pub const DEFAULT_SITE_COLOR: [u8; 4] = [255, 0, 0, 255];
+
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SiteRole {
+    Gateway,
+    Terminal,
+}
+
+/// Antenna parameters, all optional. diameter in m, gain in dBi, efficiency in (0, 1],
+/// noise temperature in K, beamwidth (3 dB) in degrees.
+#[derive(Debug, Copy, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Antenna {
+    pub diameter_m: Option<f64>,
+    pub gain_dbi: Option<f64>,
+    pub efficiency: Option<f64>,
+    pub noise_temperature_k: Option<f64>,
+    pub beamwidth_deg: Option<f64>,
+}
+
+/// A gateway or terminal from a site file.
+/// observer carries the position and elevation mask, color is RGBA for maps and plots.
+#[derive(Clone)]
+pub struct Site {
+    pub id: String,
+    pub role: SiteRole,
+    pub observer: Observer,
+    pub antenna: Antenna,
+    pub color: [u8; 4],
+}
+
+/// One entry of the sites list as written in the file.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SiteEntry {
+    id: String,
+    role: SiteRole,
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    altitude_km: f64,
+    #[serde(default = "default_elevation_mask")]
+    elevation_mask_deg: f64,
+    #[serde(default)]
+    antenna: Antenna,
+    #[serde(default = "default_color", deserialize_with = "deserialize_color")]
+    color: [u8; 4],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SiteFile {
+    sites: Vec<SiteEntry>,
+}
+
+impl Site {
+    /// sites_from_json: parses and checks a site file.
+    /// Errors name the offending entry, e.g. sites[2] (GS_US_PHX): latitude 95 is outside -90 to 90,
+    /// or the JSON path of a malformed field, e.g. sites[2].antenna.gainDbi.
+    pub fn sites_from_json(json: &str) -> anyhow::Result<Vec<Site>> {
+        let deserializer = &mut serde_json::Deserializer::from_str(json);
+        let file: SiteFile = serde_path_to_error::deserialize(deserializer)
+            .map_err(|err| anyhow::anyhow!("invalid site file at {}: {}", err.path(), err.inner()))?;
+
+        let mut sites: Vec<Site> = Vec::new();
+        for (index, entry) in file.sites.into_iter().enumerate() {
+            if let Err(err) = check_entry(&entry) {
+                anyhow::bail!("invalid site file at sites[{}] ({}): {}", index, entry.id, err);
+            }
+            if sites.iter().any(|site| site.id == entry.id) {
+                anyhow::bail!("invalid site file at sites[{}] ({}): id is already used by an earlier site", index, entry.id);
+            }
+
+            let mut observer = Observer::from_coordinates([entry.latitude, entry.longitude, entry.altitude_km]);
+            observer.elevation_mask = entry.elevation_mask_deg;
+            sites.push(Site {
+                id: entry.id,
+                role: entry.role,
+                observer,
+                antenna: entry.antenna,
+                color: entry.color,
+            });
+        }
+        Ok(sites)
+    }
+}
+
+/// load_sites: reads a site file.
+pub fn load_sites(path: &Path) -> anyhow::Result<Vec<Site>> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("could not read site file {}: {}", path.display(), err))?;
+    Site::sites_from_json(&json)
+        .map_err(|err| anyhow::anyhow!("{}: {}", path.display(), err))
+}
+
+/// check_entry: range checks of one site, the error says which field is wrong.
+fn check_entry(entry: &SiteEntry) -> Result<(), String> {
+    if entry.id.trim().is_empty() {
+        return Err("id is empty".to_string());
+    }
+    if !(-90.0..=90.0).contains(&entry.latitude) {
+        return Err(format!("latitude {} is outside -90 to 90", entry.latitude));
+    }
+    if !(-180.0..=360.0).contains(&entry.longitude) {
+        return Err(format!("longitude {} is outside -180 to 360", entry.longitude));
+    }
+    // Dead Sea shore to well above any mountain top.
+    if !(-0.5..=10.0).contains(&entry.altitude_km) {
+        return Err(format!("altitudeKm {} is outside -0.5 to 10", entry.altitude_km));
+    }
+    if !(-10.0..90.0).contains(&entry.elevation_mask_deg) {
+        return Err(format!("elevationMaskDeg {} is outside -10 to 90", entry.elevation_mask_deg));
+    }
+
+    let antenna = &entry.antenna;
+    for (name, value) in [("diameterM", antenna.diameter_m),
+                          ("noiseTemperatureK", antenna.noise_temperature_k),
+                          ("beamwidthDeg", antenna.beamwidth_deg)] {
+        if let Some(value) = value {
+            if value.is_nan() || value <= 0.0 {
+                return Err(format!("antenna.{} {} is not positive", name, value));
+            }
+        }
+    }
+    if let Some(efficiency) = antenna.efficiency {
+        if efficiency.is_nan() || efficiency <= 0.0 || efficiency > 1.0 {
+            return Err(format!("antenna.efficiency {} is outside (0, 1]", efficiency));
+        }
+    }
+    Ok(())
+}
+
+fn default_elevation_mask() -> f64 {
+    DEFAULT_MIN_ELEVATION
+}
+
+fn default_color() -> [u8; 4] {
+    DEFAULT_SITE_COLOR
+}
+
+/// Colours are "#rrggbb" or "#rrggbbaa" hex strings.
+fn deserialize_color<'de, D>(deserializer: D) -> Result<[u8; 4], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = String::deserialize(deserializer)?;
+    parse_color(&text).ok_or_else(|| serde::de::Error::custom(format!("bad colour {:?}, use #rrggbb or #rrggbbaa", text)))
+}
+
+/// parse_color: RGBA from "#rrggbb" or "#rrggbbaa", opaque when there is no alpha.
+pub fn parse_color(text: &str) -> Option<[u8; 4]> {
+    let hex = text.strip_prefix('#')?;
+    if (hex.len() != 6 && hex.len() != 8) || !hex.is_ascii() {
+        return None;
+    }
+    let mut color = [255u8; 4];
+    for (channel, index) in color.iter_mut().zip((0..hex.len()).step_by(2)) {
+        *channel = u8::from_str_radix(&hex[index..index + 2], 16).ok()?;
+    }
+    Some(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repo_site_file_loads_with_defaults() {
+        let sites = Site::sites_from_json(include_str!("../../common/sites.json")).unwrap();
+        assert_eq!(sites.len(), 2);
+        assert_eq!(sites[0].id, "GS_US_PHX");
+        assert_eq!(sites[0].role, SiteRole::Gateway);
+        assert_eq!(sites[0].observer.elevation_mask, 10.0);
+        assert_eq!(sites[0].antenna.gain_dbi, Some(62.1));
+        assert_eq!(sites[1].role, SiteRole::Terminal);
+        assert_eq!(sites[1].color, [0, 255, 255, 255]);
+
+        let minimal = Site::sites_from_json(r#"{"sites": [{"id": "UT", "role": "terminal", "latitude": 1, "longitude": 2}]}"#).unwrap();
+        assert_eq!(minimal[0].observer.elevation_mask, DEFAULT_MIN_ELEVATION);
+        assert_eq!(minimal[0].color, DEFAULT_SITE_COLOR);
+    }
+
+    #[test]
+    fn errors_name_the_entry() {
+        let entry = |id: &str, fields: &str| format!(r#"{{"id": "{}", "role": "gateway", "latitude": 1, "longitude": 2{}}}"#, id, fields);
+        let file = |entries: &[String]| format!(r#"{{"sites": [{}]}}"#, entries.join(", "));
+        let error = |json: String| Site::sites_from_json(&json).err().unwrap().to_string();
+
+        let out_of_range = error(file(&[entry("A", ""), entry("B", r#", "elevationMaskDeg": 95"#)]));
+        assert_eq!(out_of_range, "invalid site file at sites[1] (B): elevationMaskDeg 95 is outside -10 to 90");
+        let duplicate = error(file(&[entry("A", ""), entry("B", ""), entry("A", "")]));
+        assert!(duplicate.starts_with("invalid site file at sites[2] (A): id is already used"), "{}", duplicate);
+        let malformed = error(file(&[entry("A", ""), entry("B", r#", "antenna": {"gainDbi": "high"}"#)]));
+        assert!(malformed.starts_with("invalid site file at sites[1].antenna.gainDbi:"), "{}", malformed);
+        let color = error(file(&[entry("A", r##", "color": "red""##)]));
+        assert!(color.starts_with("invalid site file at sites[0].color:"), "{}", color);
+    }
+}
@@ -0,0 +1,424 @@
+//! purpose:
+//!     chart.rs renders time-series line charts of elevation, slant range, path loss and
+//!     Doppler for one observer and a set of satellites. Handover times from a link-config
+//!     plan can be overlaid as vertical markers.
+//!     Elevation is drawn while the satellite is above the horizon, the other quantities
+//!     only while it is above the observer's elevation mask.
+
+use chrono::{NaiveDateTime, NaiveTime, TimeDelta};
+use crate::lcp::plan::LinkConfigPlan;
+use crate::link::doppler::doppler_series;
+use crate::link::path_loss::{path_loss_series, CarrierFrequencies};
+use crate::obs::observer::Observer;
+use crate::render::draw::{blend_pixel, draw_line_aa};
+use crate::render::font::{draw_text, text_size};
+use crate::render::groundtrack::track_color;
+use crate::sat::satellite::Satellite;
+
+const BACKGROUND: [u8; 4] = [255, 255, 255, 255];
+const AXIS_COLOR: [u8; 4] = [40, 40, 40, 255];
+const GRID_COLOR: [u8; 4] = [225, 225, 225, 255];
+const REFERENCE_COLOR: [u8; 4] = [220, 40, 40, 255];
+const MARKER_COLOR: [u8; 4] = [90, 90, 90, 255];
+/// Series colours, the light track colours are hard to read on white so they are darkened.
+const SERIES_DARKEN: f64 = 0.75;
+
+/// Plot area margins in pixels (left, right, top, bottom) at scale 1.
+const MARGINS: (u32, u32, u32, u32) = (70, 20, 36, 40);
+
+/// Time axis tick intervals to choose from, in seconds.
+const TIME_TICKS: [i64; 12] = [60, 120, 300, 600, 900, 1800, 3600, 7200, 10800, 21600, 43200, 86400];
+
+/// The quantities that can be charted.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ChartQuantity {
+    Elevation,
+    SlantRange,
+    PathLoss,
+    Doppler,
+}
+
+impl ChartQuantity {
+    pub const ALL: [ChartQuantity; 4] = [ChartQuantity::Elevation,
+                                         ChartQuantity::SlantRange,
+                                         ChartQuantity::PathLoss,
+                                         ChartQuantity::Doppler];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            ChartQuantity::Elevation => "Elevation",
+            ChartQuantity::SlantRange => "Slant range",
+            ChartQuantity::PathLoss => "Downlink path loss",
+            ChartQuantity::Doppler => "Downlink Doppler",
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            ChartQuantity::Elevation => "deg",
+            ChartQuantity::SlantRange => "km",
+            ChartQuantity::PathLoss => "dB",
+            ChartQuantity::Doppler => "kHz",
+        }
+    }
+
+    /// file_stem: short lower case name, used for output file names.
+    pub fn file_stem(&self) -> &'static str {
+        match self {
+            ChartQuantity::Elevation => "elevation",
+            ChartQuantity::SlantRange => "range",
+            ChartQuantity::PathLoss => "path_loss",
+            ChartQuantity::Doppler => "doppler",
+        }
+    }
+}
+
+/// Chart settings.
+/// width and height of the image in pixels.
+/// step is the sampling interval of every series.
+/// carrier is used for path loss and Doppler (downlink frequency).
+#[derive(Debug, Copy, Clone)]
+pub struct ChartConfig {
+    pub width: u32,
+    pub height: u32,
+    pub step: TimeDelta,
+    pub carrier: CarrierFrequencies,
+}
+
+impl ChartConfig {
+    pub fn new(carrier: CarrierFrequencies) -> ChartConfig {
+        ChartConfig {
+            width: 1200,
+            height: 400,
+            step: TimeDelta::seconds(30),
+            carrier,
+        }
+    }
+}
+
+/// One line on a chart, None values leave a gap.
+#[derive(Debug, Clone)]
+pub struct ChartSeries {
+    pub name: String,
+    pub color: [u8; 4],
+    pub points: Vec<(NaiveDateTime, Option<f64>)>,
+}
+
+/// A labelled vertical marker, e.g. a handover.
+#[derive(Debug, Clone)]
+pub struct ChartMarker {
+    pub time: NaiveDateTime,
+    pub label: String,
+}
+
+/// A complete chart ready to be drawn.
+/// reference is an optional horizontal line, e.g. the elevation mask.
+/// y_range fixes the vertical axis, otherwise it is fitted to the data.
+#[derive(Debug, Clone)]
+pub struct LineChart {
+    pub title: String,
+    pub unit: String,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub series: Vec<ChartSeries>,
+    pub markers: Vec<ChartMarker>,
+    pub reference: Option<f64>,
+    pub y_range: Option<(f64, f64)>,
+}
+
+/// handover_markers: one marker at the start of every handover row of a plan,
+/// labelled with the satellite that takes over.
+pub fn handover_markers(plan: &LinkConfigPlan) -> Vec<ChartMarker> {
+    plan.handovers()
+        .into_iter()
+        .map(|(satellite, row)| ChartMarker {
+            time: row.start_time,
+            label: satellite.to_string(),
+        })
+        .collect()
+}
+
+/// chart_series: samples one quantity for every satellite.
+/// input: observer, satellites, start and end (UTC), quantity, chart config
+/// output: one series per satellite, coloured as on the ground track map
+pub fn chart_series(observer: &Observer,
+                    satellites: &mut [Satellite],
+                    start: &NaiveDateTime,
+                    end: &NaiveDateTime,
+                    quantity: &ChartQuantity,
+                    config: &ChartConfig) -> anyhow::Result<Vec<ChartSeries>> {
+    let mask = observer.elevation_mask;
+    let mut all_series = Vec::new();
+    for (index, satellite) in satellites.iter_mut().enumerate() {
+        let name = satellite.name();
+        let points = if *quantity == ChartQuantity::Doppler {
+            doppler_series(observer, satellite, start, end, &config.step, &config.carrier.downlink_mhz)?
+                .iter()
+                .map(|sample| (sample.time, (sample.elevation >= mask).then_some(sample.offset / 1000.0)))
+                .collect()
+        } else {
+            path_loss_series(observer, satellite, start, end, &config.step, &config.carrier)?
+                .iter()
+                .map(|sample| {
+                    let value = match quantity {
+                        ChartQuantity::Elevation => (sample.elevation >= 0.0).then_some(sample.elevation),
+                        ChartQuantity::SlantRange => (sample.elevation >= mask).then_some(sample.slant_range),
+                        _ => (sample.elevation >= mask).then_some(sample.downlink_loss),
+                    };
+                    (sample.time, value)
+                })
+                .collect()
+        };
+        let color = track_color(index);
+        all_series.push(ChartSeries {
+            name,
+            color: [(color[0] as f64 * SERIES_DARKEN) as u8,
+                    (color[1] as f64 * SERIES_DARKEN) as u8,
+                    (color[2] as f64 * SERIES_DARKEN) as u8,
+                    255],
+            points,
+        });
+    }
+    Ok(all_series)
+}
+
+/// draw_time_series_chart: samples and draws one quantity for every satellite.
+/// The elevation chart has a fixed 0 to 90 degree axis with the observer's mask as reference line.
+/// input: observer, satellites, start and end (UTC), quantity, chart config, vertical markers
+/// output: the chart image
+pub fn draw_time_series_chart(observer: &Observer,
+                              satellites: &mut [Satellite],
+                              start: &NaiveDateTime,
+                              end: &NaiveDateTime,
+                              quantity: &ChartQuantity,
+                              config: &ChartConfig,
+                              markers: &[ChartMarker]) -> anyhow::Result<image::RgbaImage> {
+    if end <= start {
+        anyhow::bail!("chart range end {} is not after start {}", end, start);
+    }
+    let elevation = *quantity == ChartQuantity::Elevation;
+    let mut title = quantity.title().to_string();
+    if !elevation {
+        let frequency = match quantity {
+            ChartQuantity::PathLoss | ChartQuantity::Doppler => format!(" at {} MHz", config.carrier.downlink_mhz),
+            _ => String::new(),
+        };
+        title = format!("{}{}, above {} deg", title, frequency, observer.elevation_mask);
+    }
+    let chart = LineChart {
+        title,
+        unit: quantity.unit().to_string(),
+        start: *start,
+        end: *end,
+        series: chart_series(observer, satellites, start, end, quantity, config)?,
+        markers: markers.to_vec(),
+        reference: elevation.then_some(observer.elevation_mask),
+        y_range: elevation.then_some((0.0, 90.0)),
+    };
+    draw_line_chart(&chart, config.width, config.height)
+}
+
+/// draw_line_chart: draws a line chart with a time axis, legend and markers.
+/// input: chart, image width and height in pixels
+/// output: the chart image
+pub fn draw_line_chart(chart: &LineChart, width: u32, height: u32) -> anyhow::Result<image::RgbaImage> {
+    if width < 200 || height < 120 {
+        anyhow::bail!("chart size {}x{} is too small, use at least 200x120 pixels", width, height);
+    }
+    if chart.end <= chart.start {
+        anyhow::bail!("chart range end {} is not after start {}", chart.end, chart.start);
+    }
+    let mut image = image::RgbaImage::from_pixel(width, height, image::Rgba(BACKGROUND));
+    let scale = (height / 400).max(1);
+    let left = (MARGINS.0 * scale) as f64;
+    let right = width as f64 - (MARGINS.1 * scale) as f64;
+    let top = (MARGINS.2 * scale) as f64;
+    let bottom = height as f64 - (MARGINS.3 * scale) as f64;
+
+    let (y_min, y_max) = chart.y_range.unwrap_or_else(|| value_range(chart));
+    let span = (chart.end - chart.start).num_milliseconds() as f64;
+    let x_of = |time: &NaiveDateTime| left + (*time - chart.start).num_milliseconds() as f64 / span * (right - left);
+    let y_of = |value: f64| bottom - (value - y_min) / (y_max - y_min) * (bottom - top);
+
+    // Value axis grid and labels.
+    let y_step = nice_step((y_max - y_min) / 5.0);
+    let mut tick = (y_min / y_step).ceil() * y_step;
+    while tick <= y_max + y_step * 1e-9 {
+        let y = y_of(tick).round() + 0.5;
+        draw_line_aa(&mut image, (left, y), (right, y), &GRID_COLOR);
+        let label = format_value(tick, y_step);
+        let (label_width, label_height) = text_size(&label, scale);
+        draw_text(&mut image,
+                  left as i64 - 6 - label_width as i64,
+                  y as i64 - label_height as i64 / 2,
+                  &label, &AXIS_COLOR, scale);
+        tick += y_step;
+    }
+
+    // Time axis grid and labels.
+    let tick_seconds = TIME_TICKS.iter()
+        .cloned()
+        .find(|seconds| span / 1000.0 / *seconds as f64 <= 8.0)
+        .unwrap_or(86400 * ((span / 1000.0 / 86400.0 / 8.0).ceil() as i64).max(1));
+    let multi_day = chart.end.date() != chart.start.date();
+    let midnight = chart.start.date().and_time(NaiveTime::MIN);
+    let first = ((chart.start - midnight).num_seconds() + tick_seconds - 1) / tick_seconds * tick_seconds;
+    let mut time = midnight + TimeDelta::seconds(first);
+    while time <= chart.end {
+        let x = x_of(&time).round() + 0.5;
+        draw_line_aa(&mut image, (x, top), (x, bottom), &GRID_COLOR);
+        let label = if multi_day { time.format("%m-%d %H:%M") } else { time.format("%H:%M") }.to_string();
+        let (label_width, _) = text_size(&label, scale);
+        draw_text(&mut image, x as i64 - label_width as i64 / 2, bottom as i64 + 6, &label, &AXIS_COLOR, scale);
+        time += TimeDelta::seconds(tick_seconds);
+    }
+
+    if let Some(reference) = chart.reference {
+        if reference >= y_min && reference <= y_max {
+            let y = y_of(reference);
+            draw_line_aa(&mut image, (left, y), (right, y), &REFERENCE_COLOR);
+        }
+    }
+
+    for marker in chart.markers.iter().filter(|marker| marker.time >= chart.start && marker.time <= chart.end) {
+        let x = x_of(&marker.time).round() as i64;
+        draw_dashed_vertical(&mut image, x, top as i64, bottom as i64, &MARKER_COLOR);
+        let (label_width, _) = text_size(&marker.label, scale);
+        let label_x = if x + 2 + label_width as i64 > width as i64 { x - 2 - label_width as i64 } else { x + 2 };
+        draw_text(&mut image, label_x, top as i64 + 2, &marker.label, &MARKER_COLOR, scale);
+    }
+
+    // Series, split wherever a value is missing.
+    for series in &chart.series {
+        let mut previous: Option<(f64, f64)> = None;
+        for (time, value) in &series.points {
+            let point = value.map(|value| (x_of(time), y_of(value.clamp(y_min, y_max))));
+            if let (Some(from), Some(to)) = (previous, point) {
+                draw_line_aa(&mut image, from, to, &series.color);
+            }
+            previous = point;
+        }
+    }
+
+    // Plot frame, title, unit and legend.
+    draw_line_aa(&mut image, (left, top), (left, bottom), &AXIS_COLOR);
+    draw_line_aa(&mut image, (left, bottom), (right, bottom), &AXIS_COLOR);
+    let title = format!("{} ({})", chart.title, chart.unit);
+    draw_text(&mut image, left as i64, 8 * scale as i64, &title, &AXIS_COLOR, scale);
+    let range = format!("{} - {} UTC", chart.start.format("%Y-%m-%d %H:%M"), chart.end.format("%Y-%m-%d %H:%M"));
+    let (range_width, range_height) = text_size(&range, scale);
+    draw_text(&mut image,
+              right as i64 - range_width as i64,
+              height as i64 - 4 - range_height as i64,
+              &range, &AXIS_COLOR, scale);
+    let mut legend_x = right as i64;
+    for series in chart.series.iter().rev() {
+        let (name_width, _) = text_size(&series.name, scale);
+        legend_x -= name_width as i64;
+        draw_text(&mut image, legend_x, 8 * scale as i64, &series.name, &series.color, scale);
+        legend_x -= 10 * scale as i64;
+    }
+    Ok(image)
+}
+
+/// value_range: range of all values in a chart with 5% padding, (0, 1) when there are none.
+fn value_range(chart: &LineChart) -> (f64, f64) {
+    let values = chart.series.iter().flat_map(|series| series.points.iter().filter_map(|(_, value)| *value));
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| (min.min(value), max.max(value)));
+    if !min.is_finite() {
+        return (0.0, 1.0);
+    }
+    let padding = if max > min { (max - min) * 0.05 } else { min.abs().max(1.0) * 0.05 };
+    (min - padding, max + padding)
+}
+
+/// nice_step: smallest 1, 2 or 5 times a power of ten that is at least rough_step.
+fn nice_step(rough_step: f64) -> f64 {
+    let magnitude = 10f64.powf(rough_step.log10().floor());
+    [1.0, 2.0, 5.0, 10.0].iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= rough_step)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// format_value: axis label with as many decimals as the tick step needs.
+fn format_value(value: f64, step: f64) -> String {
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    format!("{:.*}", decimals, value)
+}
+
+/// draw_dashed_vertical: vertical dashed line, 4 pixels on and 4 off.
+fn draw_dashed_vertical(image: &mut image::RgbaImage, x: i64, top: i64, bottom: i64, color: &[u8; 4]) {
+    for y in top..=bottom {
+        if (y - top) % 8 < 4 {
+            blend_pixel(image, x, y, color, 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::sat::tle::parse_tles;
+
+    fn config() -> ChartConfig {
+        ChartConfig::new(CarrierFrequencies { uplink_mhz: 2025.0, downlink_mhz: 2200.0 })
+    }
+
+    #[test]
+    fn series_only_carry_values_while_visible() {
+        let start = NaiveDate::from_ymd_opt(2012, 10, 15).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let end = start + TimeDelta::days(1);
+        let mut observer = Observer::from_coordinates([33.4484, -112.0740, 0.331]);
+        observer.elevation_mask = 10.0;
+        let mut satellites = vec![Satellite::new(parse_tles(include_str!("../../common/tle2.txt")).elements.remove(0))];
+
+        let elevation = chart_series(&observer, &mut satellites, &start, &end, &ChartQuantity::Elevation, &config()).unwrap();
+        let range = chart_series(&observer, &mut satellites, &start, &end, &ChartQuantity::SlantRange, &config()).unwrap();
+        assert_eq!(elevation.len(), 1);
+        assert_eq!(elevation[0].points.len(), range[0].points.len());
+        // A day of 30 s samples, with a gap whenever the satellite is below the horizon (elevation) or mask (others).
+        assert_eq!(elevation[0].points.len(), 2881);
+        let above_horizon = elevation[0].points.iter().filter_map(|(_, value)| *value).collect::<Vec<_>>();
+        assert!(!above_horizon.is_empty() && above_horizon.len() < 2881);
+        assert!(above_horizon.iter().all(|value| (0.0..=90.0).contains(value)));
+        for ((_, elevation), (_, range)) in elevation[0].points.iter().zip(&range[0].points) {
+            assert_eq!(range.is_some(), elevation.is_some_and(|elevation| elevation >= 10.0));
+            assert!(range.is_none_or(|range| (500.0..3500.0).contains(&range)), "{:?} km", range);
+        }
+
+        let image = draw_time_series_chart(&observer, &mut satellites, &start, &end, &ChartQuantity::Elevation,
+                                           &config(), &[]).unwrap();
+        assert_eq!(image.dimensions(), (1200, 400));
+        assert!(draw_time_series_chart(&observer, &mut satellites, &end, &start, &ChartQuantity::Elevation,
+                                       &config(), &[]).is_err());
+    }
+
+    #[test]
+    fn line_chart_draws_series_and_markers() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let end = start + TimeDelta::hours(1);
+        let chart = LineChart {
+            title: "Test".to_string(),
+            unit: "dB".to_string(),
+            start,
+            end,
+            series: vec![ChartSeries {
+                name: "MP01".to_string(),
+                color: [0, 0, 200, 255],
+                points: vec![(start, Some(0.0)), (start + TimeDelta::minutes(30), Some(1.0)), (end, None)],
+            }],
+            markers: vec![ChartMarker { time: start + TimeDelta::minutes(45), label: "MP02".to_string() }],
+            reference: None,
+            y_range: None,
+        };
+        let image = draw_line_chart(&chart, 400, 200).unwrap();
+        assert_eq!(image.dimensions(), (400, 200));
+        assert!(image.pixels().any(|pixel| pixel.0[2] > 150 && pixel.0[0] < 100), "series not drawn");
+        assert!(image.pixels().any(|pixel| pixel.0 == MARKER_COLOR), "marker not drawn");
+
+        assert!(draw_line_chart(&chart, 100, 100).is_err());
+        assert!(draw_line_chart(&LineChart { end: start, ..chart }, 400, 200).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! purpose:
+//!     draw.rs holds the low level drawing primitives shared by the map and plot renderers.
+
+/// blend_pixel: alpha blends a colour into a pixel, pixels outside the image are ignored.
+/// input: image, pixel x/y, colour, coverage of the pixel in [0, 1]
+pub fn blend_pixel(image: &mut image::RgbaImage, x: i64, y: i64, color: &[u8; 4], coverage: f64) {
+    if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+        return;
+    }
+    let alpha = coverage.clamp(0.0, 1.0) * color[3] as f64 / 255.0;
+    let pixel = image.get_pixel_mut(x as u32, y as u32);
+    for (channel, source) in pixel.0.iter_mut().zip(color.iter()).take(3) {
+        let blended = *source as f64 * alpha + *channel as f64 * (1.0 - alpha);
+        *channel = blended.round() as u8;
+    }
+    pixel.0[3] = pixel.0[3].max((alpha * 255.0).round() as u8);
+}
+
+/// draw_line_aa: anti-aliased line between two sub-pixel points (Xiaolin Wu).
+/// input: image, start x/y, end x/y, colour
+pub fn draw_line_aa(image: &mut image::RgbaImage, from: (f64, f64), to: (f64, f64), color: &[u8; 4]) {
+    let (mut x0, mut y0) = from;
+    let (mut x1, mut y1) = to;
+    let steep = (y1 - y0).abs() > (x1 - x0).abs();
+    if steep {
+        core::mem::swap(&mut x0, &mut y0);
+        core::mem::swap(&mut x1, &mut y1);
+    }
+    if x0 > x1 {
+        core::mem::swap(&mut x0, &mut x1);
+        core::mem::swap(&mut y0, &mut y1);
+    }
+
+    let dx = x1 - x0;
+    let gradient = if dx == 0.0 { 0.0 } else { (y1 - y0) / dx };
+    let mut plot = |x: i64, y: i64, coverage: f64| {
+        if steep {
+            blend_pixel(image, y, x, color, coverage);
+        } else {
+            blend_pixel(image, x, y, color, coverage);
+        }
+    };
+
+    let start = x0.round() as i64;
+    let end = x1.round() as i64;
+    for x in start..=end {
+        let y = y0 + gradient * (x as f64 - x0);
+        let base = y.floor();
+        let fraction = y - base;
+        plot(x, base as i64, 1.0 - fraction);
+        plot(x, base as i64 + 1, fraction);
+    }
+}
+
+/// draw_polyline_aa: anti-aliased line through consecutive points.
+pub fn draw_polyline_aa(image: &mut image::RgbaImage, points: &[(f64, f64)], color: &[u8; 4]) {
+    for pair in points.windows(2) {
+        draw_line_aa(image, pair[0], pair[1], color);
+    }
+}
+
+/// draw_circle_aa: anti-aliased circle outline.
+/// input: image, centre x/y, radius in pixels, colour
+pub fn draw_circle_aa(image: &mut image::RgbaImage, center: (f64, f64), radius: f64, color: &[u8; 4]) {
+    let segments = ((radius * core::f64::consts::TAU / 2.0).ceil() as usize).max(16);
+    let points: Vec<(f64, f64)> = (0..=segments)
+        .map(|i| {
+            let angle = i as f64 * core::f64::consts::TAU / segments as f64;
+            (center.0 + radius * angle.cos(), center.1 + radius * angle.sin())
+        })
+        .collect();
+    draw_polyline_aa(image, &points, color);
+}
@@ -0,0 +1,102 @@
+//! purpose:
+//!     footprint.rs draws satellite visibility footprints on the earth map, as outlines
+//!     with optional shading of the region that has line of sight.
+
+use crate::coordinate_systems::Geodetic;
+use crate::render::draw::blend_pixel;
+use crate::render::groundtrack::draw_ground_track;
+use crate::render::projection::Projection;
+use crate::vis::footprint::{footprint_contour, in_footprint};
+
+/// Number of vertices used for each contour.
+const CONTOUR_VERTICES: usize = 360;
+
+/// draw_footprint: draws the footprint outline for one minimum elevation.
+/// input: map image, projection of the image, sub-satellite point (with altitude), minimum elevation in degrees,
+///        outline colour, shading alpha (None for outline only)
+pub fn draw_footprint(image: &mut image::RgbaImage,
+                      projection: &dyn Projection,
+                      sub_point: &Geodetic,
+                      min_elevation: &f64,
+                      color: &[u8; 4],
+                      shade: Option<u8>) {
+    if let Some(alpha) = shade {
+        shade_footprint(image, projection, sub_point, min_elevation, &[color[0], color[1], color[2], alpha]);
+    }
+    let contour = footprint_contour(sub_point, min_elevation, CONTOUR_VERTICES);
+    draw_ground_track(image, projection, &contour, color);
+}
+
+/// draw_footprints: nested footprint outlines, e.g. for 10, 20 and 30 degrees elevation.
+/// Only the lowest elevation (largest footprint) is shaded.
+pub fn draw_footprints(image: &mut image::RgbaImage,
+                       projection: &dyn Projection,
+                       sub_point: &Geodetic,
+                       min_elevations: &[f64],
+                       color: &[u8; 4],
+                       shade: Option<u8>) {
+    let lowest = min_elevations.iter().cloned().fold(f64::INFINITY, f64::min);
+    for elevation in min_elevations {
+        let shade = if *elevation == lowest { shade } else { None };
+        draw_footprint(image, projection, sub_point, elevation, color, shade);
+    }
+}
+
+/// shade_footprint: blends a colour over every map pixel inside the footprint.
+/// Testing pixels directly keeps footprints over a pole or the antimeridian correct.
+fn shade_footprint(image: &mut image::RgbaImage,
+                   projection: &dyn Projection,
+                   sub_point: &Geodetic,
+                   min_elevation: &f64,
+                   color: &[u8; 4]) {
+    let (width, height) = image.dimensions();
+    for y in 0..height {
+        for x in 0..width {
+            let (longitude, latitude) = match projection.inverse(x as f64 + 0.5, y as f64 + 0.5) {
+                Some(lon_lat) => lon_lat,
+                None => continue,
+            };
+            let ground = Geodetic { latitude, longitude, altitude: 0.0 };
+            if in_footprint(sub_point, &ground, min_elevation) {
+                blend_pixel(image, x as i64, y as i64, color, 1.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::projection::Equirectangular;
+
+    const WHITE: image::Rgba<u8> = image::Rgba([255, 255, 255, 255]);
+
+    #[test]
+    fn only_the_lowest_footprint_is_shaded() {
+        // One pixel per degree, so longitude L on the equator is pixel (180 + L, 90).
+        let projection = Equirectangular { width: 360, height: 180 };
+        let sub_point = Geodetic { latitude: 0.0, longitude: 0.0, altitude: 1200.0 };
+        let color = [0, 0, 255, 255];
+        let draw = |elevations: &[f64]| {
+            let mut image = image::RgbaImage::from_pixel(360, 180, WHITE);
+            draw_footprints(&mut image, &projection, &sub_point, elevations, &color, Some(100));
+            image
+        };
+
+        // The 30, 20 and 10 degree edges are 13.2, 17.7 and 24.0 degrees from the sub-satellite point.
+        let nested = draw(&[30.0, 10.0, 20.0]);
+        let inside = *nested.get_pixel(185, 90);
+        assert_ne!(inside, WHITE);
+        assert_eq!(*nested.get_pixel(195, 90), inside, "the ring between 30 and 20 degrees is shaded differently");
+        assert_eq!(*nested.get_pixel(220, 90), WHITE);
+        // Shaded once, not once per contour.
+        assert_eq!(*draw(&[10.0]).get_pixel(185, 90), inside);
+
+        // Outlines are drawn for every contour, at the right distance.
+        let outlined = |x: u32| (x - 1..=x + 1).any(|x| nested.get_pixel(x, 90).0[0] < inside.0[0]);
+        for edge in [180 + 13, 180 - 13, 180 + 17, 180 + 24, 180 - 24] {
+            assert!(outlined(edge), "no outline near pixel {}", edge);
+        }
+        assert!(!outlined(185) && !outlined(210));
+    }
+}
@@ -0,0 +1,166 @@
+//! purpose:
+//!     groundtrack.rs samples a satellite's sub-satellite point over time and draws
+//!     the resulting track on the earth map. On cylindrical projections tracks are split
+//!     where they cross the antimeridian so no segment is drawn across the whole map.
+
+use chrono::{NaiveDateTime, TimeDelta};
+use crate::coordinate_systems::Geodetic;
+use crate::render::draw::draw_polyline_aa;
+use crate::render::projection::Projection;
+use crate::sat::satellite::Satellite;
+
+/// Track colours, cycled through by satellite index.
+pub const TRACK_COLORS: [[u8; 4]; 8] = [
+    [255, 215, 0, 255],   // gold
+    [0, 191, 255, 255],   // deep sky blue
+    [255, 105, 180, 255], // hot pink
+    [50, 205, 50, 255],   // lime green
+    [255, 140, 0, 255],   // dark orange
+    [186, 85, 211, 255],  // orchid
+    [0, 255, 255, 255],   // cyan
+    [255, 255, 255, 255], // white
+];
+
+pub fn track_color(index: usize) -> [u8; 4] {
+    TRACK_COLORS[index % TRACK_COLORS.len()]
+}
+
+/// ground_track: sub-satellite points sampled every step from start to end.
+/// input: satellite, start and end (UTC), step
+/// output: geodetic points (degrees, km), the last point is at end
+pub fn ground_track(satellite: &mut Satellite,
+                    start: &NaiveDateTime,
+                    end: &NaiveDateTime,
+                    step: &TimeDelta) -> anyhow::Result<Vec<Geodetic>> {
+    if end < start {
+        anyhow::bail!("ground track end {} is before start {}", end, start);
+    }
+    if *step <= TimeDelta::zero() {
+        anyhow::bail!("ground track step must be positive");
+    }
+
+    let mut track = Vec::new();
+    let mut t = *start;
+    loop {
+        satellite.propagate(&t)?;
+        track.push(satellite.geodetic_coordinates);
+        if t >= *end {
+            break;
+        }
+        t = (t + *step).min(*end);
+    }
+    Ok(track)
+}
+
+/// split_at_antimeridian: splits a track into runs that do not cross +-180 longitude.
+/// The crossing point is interpolated and added to both sides so runs meet the map edge.
+pub fn split_at_antimeridian(track: &[Geodetic]) -> Vec<Vec<Geodetic>> {
+    let mut runs: Vec<Vec<Geodetic>> = Vec::new();
+    let mut current: Vec<Geodetic> = Vec::new();
+
+    for point in track {
+        if let Some(last) = current.last().copied() {
+            let delta = point.longitude - last.longitude;
+            if delta.abs() > 180.0 {
+                // Unwrap the new longitude next to the last one and find the edge crossing.
+                let edge = if last.longitude > 0.0 { 180.0 } else { -180.0 };
+                let unwrapped = point.longitude + if delta < 0.0 { 360.0 } else { -360.0 };
+                let fraction = (edge - last.longitude) / (unwrapped - last.longitude);
+                let latitude = last.latitude + fraction * (point.latitude - last.latitude);
+                let altitude = last.altitude + fraction * (point.altitude - last.altitude);
+
+                current.push(Geodetic { latitude, longitude: edge, altitude });
+                runs.push(core::mem::take(&mut current));
+                current.push(Geodetic { latitude, longitude: -edge, altitude });
+            }
+        }
+        current.push(*point);
+    }
+    if !current.is_empty() {
+        runs.push(current);
+    }
+    runs
+}
+
+/// draw_ground_track: draws a track as anti-aliased polylines on the map.
+/// The track is broken wherever the projection cannot show it or breaks it, and at the antimeridian
+/// on projections that wrap.
+/// input: map image, projection of the image, track, colour
+pub fn draw_ground_track(image: &mut image::RgbaImage, projection: &dyn Projection, track: &[Geodetic], color: &[u8; 4]) {
+    let runs = if projection.wraps_longitude() {
+        split_at_antimeridian(track)
+    } else {
+        vec![track.to_vec()]
+    };
+
+    for run in runs {
+        let mut points: Vec<(f64, f64)> = Vec::new();
+        let mut last: Option<&Geodetic> = None;
+        for point in &run {
+            let xy = projection.forward(point.longitude, point.latitude);
+            if xy.is_none() || last.is_some_and(|last| projection.breaks_between(last, point)) {
+                draw_polyline_aa(image, &points, color);
+                points.clear();
+            }
+            if let Some(xy) = xy {
+                points.push(xy);
+            }
+            last = Some(point);
+        }
+        draw_polyline_aa(image, &points, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::projection::Equirectangular;
+
+    fn track(points: &[(f64, f64)]) -> Vec<Geodetic> {
+        points.iter().map(|(longitude, latitude)| Geodetic { latitude: *latitude, longitude: *longitude, altitude: 700.0 }).collect()
+    }
+
+    fn longitudes(run: &[Geodetic]) -> Vec<f64> {
+        run.iter().map(|point| point.longitude).collect()
+    }
+
+    #[test]
+    fn tracks_split_where_they_cross_the_antimeridian() {
+        // Eastward across +180, the crossing is a third of the way from 178 to 184 (-176).
+        let east = split_at_antimeridian(&track(&[(170.0, 10.0), (178.0, 12.0), (-176.0, 14.0), (-170.0, 16.0)]));
+        assert_eq!(east.len(), 2);
+        assert_eq!(longitudes(&east[0]), [170.0, 178.0, 180.0]);
+        assert_eq!(longitudes(&east[1]), [-180.0, -176.0, -170.0]);
+        // Westward across -180.
+        let west = split_at_antimeridian(&track(&[(-175.0, -20.0), (-179.0, -22.0), (177.0, -24.0)]));
+        assert_eq!(west.len(), 2);
+        assert_eq!(longitudes(&west[0]), [-175.0, -179.0, -180.0]);
+        assert_eq!(longitudes(&west[1]), [180.0, 177.0]);
+
+        for (runs, latitude) in [(&east, 12.0 + 2.0 / 3.0), (&west, -22.5)] {
+            assert!((runs[0].last().unwrap().latitude - latitude).abs() < 1e-12);
+            assert!((runs[1][0].latitude - latitude).abs() < 1e-12);
+            for run in runs.iter() {
+                assert!(run.windows(2).all(|pair| (pair[1].longitude - pair[0].longitude).abs() < 180.0));
+            }
+        }
+
+        let across_greenwich = track(&[(-10.0, 50.0), (-2.0, 51.0), (5.0, 52.0)]);
+        let runs = split_at_antimeridian(&across_greenwich);
+        assert_eq!(runs.len(), 1);
+        assert_eq!(longitudes(&runs[0]), longitudes(&across_greenwich));
+    }
+
+    #[test]
+    fn drawn_track_does_not_span_the_map() {
+        let projection = Equirectangular { width: 360, height: 180 };
+        let background = image::Rgba([0, 0, 0, 255]);
+        let mut image = image::RgbaImage::from_pixel(360, 180, background);
+        draw_ground_track(&mut image, &projection, &track(&[(170.0, 10.0), (-170.0, 12.0)]), &[255, 255, 255, 255]);
+        // Only the ten pixel stubs next to either edge are drawn, around rows 78 to 80.
+        let drawn: Vec<u32> = (0..360).filter(|x| (76..82).any(|y| *image.get_pixel(*x, y) != background)).collect();
+        assert!(!drawn.is_empty());
+        assert!(drawn.iter().all(|x| *x <= 11 || *x >= 348), "{:?}", drawn);
+        assert!(drawn.contains(&0) && drawn.contains(&359));
+    }
+}
@@ -0,0 +1,116 @@
+//! purpose:
+//!     map.rs loads the earth map image and maps geodetic points onto its pixels.
+//!     The map file is an equirectangular (plate carree) image covering -180..180 longitude
+//!     and 90..-90 latitude, see projection.rs to draw it in other projections.
+
+use image::GenericImageView;
+use crate::render::projection::Projection;
+
+/// load_map: loads a map into memory and creates a new map for editing.
+/// input: filename
+/// output: new rgbaimage, height, width
+pub fn load_map(filename : &str) -> anyhow::Result<(image::RgbaImage, u32, u32)> { 
+    let input_image = image::open(filename)?;
+    let (width, height) = input_image.dimensions();
+    let mut img: image::RgbaImage = image::ImageBuffer::new(width, height);
+    
+    // Create the output map.
+    // TODO: parallelize this
+    for x_iter in 0..input_image.width() { 
+        for y_iter in 0..input_image.height() {
+            let color = input_image.get_pixel(x_iter,y_iter);
+            
+            img.put_pixel(x_iter,y_iter,color);
+        }
+    }
+   Ok((img, height, width))
+}
+
+/// gimme_point:
+/// input: projection, longitude, latitude (degrees)
+/// output: x,y pixel the point falls in.
+pub fn gimme_point(projection: &dyn Projection, lon : &f64, lat : &f64) -> anyhow::Result<(u32, u32)> { 
+    let (width, height) = projection.dimensions();
+    let (x, y) = match projection.forward(*lon, *lat) {
+        Some(point) => point,
+        None => anyhow::bail!("point ({}, {}) is not shown by the map projection", lon, lat),
+    };
+    // The right/bottom edges belong to the last column/row.
+    let x = (x.floor() as u32).min(width.saturating_sub(1));
+    let y = (y.floor() as u32).min(height.saturating_sub(1));
+    Ok((x,y))
+}
+
+/// get_pixel_vector: 
+/// input: height, width, where we want the pixel centered on, is_sat flag
+/// output: vector containing pixel locations
+/// Note: I hate this function, I need to spend more time thinking about it.
+/// Fat pixel Ex: 
+///             ***
+///             ***
+///             ***
+pub fn get_pixel_vector(height: &u32, width: &u32, center_pixel: &(u32, u32), is_sat: bool) -> anyhow::Result<Vec<(u32, u32)>> {
+    let mut pixel_vector = vec![];
+    let x = center_pixel.0;
+    let y = center_pixel.1;
+
+    let center      = (x,y);
+    pixel_vector.push(center);
+
+    // Only plot a fat pixel for observer locations
+    if !is_sat {
+        // Neighbours past the last row/column fall back to the center so the fat pixel stays on the image.
+        let below = y + 1 < *height;
+        let after = x + 1 < *width;
+        let top         = if below                      {(x, y+1)}   else {(x,y)};
+        let bot         = if y > 0                      {(x, y-1)}   else {(x,y)};
+        let left        = if x > 0                      {(x-1, y)}   else {(x,y)};
+        let right       = if after                      {(x+1, y)}   else {(x,y)};
+        let top_right   = if after && y > 0             {(x+1, y-1)} else {(x,y)};
+        let top_left    = if x > 0 && y > 0             {(x-1, y-1)} else {(x,y)};
+        let bot_right   = if after && below             {(x+1, y+1)} else {(x,y)};
+        let bot_left    = if x > 0 && below             {(x-1, y+1)} else {(x,y)};
+
+        pixel_vector.push(top);
+        pixel_vector.push(bot);
+        pixel_vector.push(left);
+        pixel_vector.push(right); 
+        pixel_vector.push(top_right);
+        pixel_vector.push(top_left);
+        pixel_vector.push(bot_right);
+        pixel_vector.push(bot_left);
+    }
+    Ok(pixel_vector)
+}
+
+/// color_pixel: colors a pixel in the provided image reference.
+/// input: image, pixel_coordinates, color
+/// output: none
+pub fn color_pixel(image: &mut image::RgbaImage, pixel_coordinates: &(u32, u32), color: &[u8; 4]) -> anyhow::Result<()>{ 
+    let (width, height) = image.dimensions();
+    if pixel_coordinates.0 >= width { 
+        anyhow::bail!("x coordinate pixel outside range of image");
+    }
+    if pixel_coordinates.1 >= height { 
+        anyhow::bail!("y coord pixel outside bounds of image.");
+    }
+    image.put_pixel(pixel_coordinates.0, pixel_coordinates.1, image::Rgba(*color));
+    
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fat_pixels_stay_on_the_image() {
+        let (width, height) = (8u32, 4u32);
+        for center in [(0, 0), (7, 3), (7, 0), (0, 3), (3, 2)] {
+            let pixels = get_pixel_vector(&height, &width, &center, false).unwrap();
+            assert_eq!(pixels.len(), 9);
+            assert!(pixels.iter().all(|pixel| pixel.0 < width && pixel.1 < height), "{:?}: {:?}", center, pixels);
+        }
+    }
+}
@@ -0,0 +1,8 @@
+pub mod draw;
+pub mod map;
+pub mod projection;
+pub mod groundtrack;
+pub mod footprint;
+pub mod font;
+pub mod skyplot;
+pub mod chart;
\ No newline at end of file
@@ -0,0 +1,309 @@
+//! purpose:
+//!     projection.rs maps geodetic points onto image pixels and back.
+//!     Every map drawing routine takes a &dyn Projection so tracks, footprints and
+//!     observers can be drawn on any of:
+//!         Equirectangular       plate carree, the projection of BigEarth.jpg
+//!         WebMercator           latitudes clipped to +-85.05 degrees
+//!         AzimuthalEquidistant  centred on an observer (e.g. a gateway), true distance
+//!                               and azimuth from the centre, the whole earth in a disc
+//!     Pixel coordinates are continuous, (0, 0) is the top left corner of the image.
+
+use crate::coordinate_systems::Geodetic;
+use crate::utils::satutils::{degrees_to_radians, radians_to_degrees};
+
+/// Latitude limit of Web Mercator, where the map becomes square.
+pub const WEB_MERCATOR_MAX_LATITUDE: f64 = 85.05112877980659;
+
+/// Largest change of bearing, degrees, between two joined points on the far side of an azimuthal map.
+/// A LEO track sampled every minute turns a few degrees per step unless it passes within a few
+/// degrees of the antipode.
+const MAX_FAR_SIDE_TURN: f64 = 30.0;
+
+pub trait Projection {
+    /// forward: pixel position of a point, None if the projection cannot show it.
+    /// input: longitude, latitude in degrees
+    fn forward(&self, longitude: f64, latitude: f64) -> Option<(f64, f64)>;
+
+    /// inverse: (longitude, latitude) in degrees of a pixel position,
+    /// None if the pixel is outside the projected earth.
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)>;
+
+    /// dimensions: width and height of the image in pixels.
+    fn dimensions(&self) -> (u32, u32);
+
+    /// wraps_longitude: true for cylindrical projections, where the antimeridian
+    /// is the left/right image edge and lines crossing it must be split there.
+    fn wraps_longitude(&self) -> bool;
+
+    /// breaks_between: true when a straight image segment between two nearby points would cut
+    /// across the map instead of following them, e.g. past the antipode of an azimuthal map.
+    /// Lines are broken there. Antimeridian crossings are left to wraps_longitude.
+    fn breaks_between(&self, _from: &Geodetic, _to: &Geodetic) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Equirectangular {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Projection for Equirectangular {
+    fn forward(&self, longitude: f64, latitude: f64) -> Option<(f64, f64)> {
+        if !(-90.0..=90.0).contains(&latitude) {
+            return None;
+        }
+        let longitude = edge_longitude(longitude);
+        Some((self.width as f64 * (180.0 + longitude) / 360.0,
+              self.height as f64 * (90.0 - latitude) / 180.0))
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        if !in_image(x, y, self.width, self.height) {
+            return None;
+        }
+        Some((360.0 * x / self.width as f64 - 180.0,
+              90.0 - 180.0 * y / self.height as f64))
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn wraps_longitude(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct WebMercator {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Projection for WebMercator {
+    fn forward(&self, longitude: f64, latitude: f64) -> Option<(f64, f64)> {
+        if latitude.abs() > WEB_MERCATOR_MAX_LATITUDE {
+            return None;
+        }
+        let longitude = edge_longitude(longitude);
+        let phi = degrees_to_radians(&latitude);
+        let mercator_y = (core::f64::consts::FRAC_PI_4 + phi / 2.0).tan().ln();
+        Some((self.width as f64 * (180.0 + longitude) / 360.0,
+              self.height as f64 / 2.0 * (1.0 - mercator_y / core::f64::consts::PI)))
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        if !in_image(x, y, self.width, self.height) {
+            return None;
+        }
+        let mercator_y = core::f64::consts::PI * (1.0 - 2.0 * y / self.height as f64);
+        let phi = 2.0 * mercator_y.exp().atan() - core::f64::consts::FRAC_PI_2;
+        Some((360.0 * x / self.width as f64 - 180.0, radians_to_degrees(&phi)))
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn wraps_longitude(&self) -> bool {
+        true
+    }
+}
+
+/// The whole earth fits in a disc of diameter min(width, height), the antipode of
+/// the centre is the disc edge.
+#[derive(Debug, Copy, Clone)]
+pub struct AzimuthalEquidistant {
+    pub width: u32,
+    pub height: u32,
+    pub center: Geodetic,
+}
+
+impl AzimuthalEquidistant {
+    /// Pixels per radian of central angle.
+    fn scale(&self) -> f64 {
+        self.width.min(self.height) as f64 / 2.0 / core::f64::consts::PI
+    }
+
+    /// polar: central angle from the centre and bearing from north of a point, radians.
+    fn polar(&self, longitude: f64, latitude: f64) -> (f64, f64) {
+        let phi0 = degrees_to_radians(&self.center.latitude);
+        let phi = degrees_to_radians(&latitude);
+        let dlambda = degrees_to_radians(&(longitude - self.center.longitude));
+        let cos_c = phi0.sin() * phi.sin() + phi0.cos() * phi.cos() * dlambda.cos();
+        let bearing = (phi.cos() * dlambda.sin()).atan2(phi0.cos() * phi.sin() - phi0.sin() * phi.cos() * dlambda.cos());
+        (cos_c.clamp(-1.0, 1.0).acos(), bearing)
+    }
+}
+
+impl Projection for AzimuthalEquidistant {
+    fn forward(&self, longitude: f64, latitude: f64) -> Option<(f64, f64)> {
+        if !(-90.0..=90.0).contains(&latitude) {
+            return None;
+        }
+        let (c, bearing) = self.polar(longitude, latitude);
+        // The antipode has no single position.
+        if core::f64::consts::PI - c < 1e-9 {
+            return None;
+        }
+        // Distance from the centre is the central angle, the direction is the bearing.
+        let (x, y) = (c * bearing.sin(), c * bearing.cos());
+
+        Some((self.width as f64 / 2.0 + x * self.scale(),
+              self.height as f64 / 2.0 - y * self.scale()))
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let x = (x - self.width as f64 / 2.0) / self.scale();
+        let y = (self.height as f64 / 2.0 - y) / self.scale();
+        let rho = (x * x + y * y).sqrt();
+        if rho > core::f64::consts::PI {
+            return None;
+        }
+        if rho < 1e-12 {
+            return Some((self.center.longitude, self.center.latitude));
+        }
+
+        let phi0 = degrees_to_radians(&self.center.latitude);
+        let (sin_c, cos_c) = rho.sin_cos();
+        let phi = (cos_c * phi0.sin() + y * sin_c * phi0.cos() / rho).clamp(-1.0, 1.0).asin();
+        let dlambda = (x * sin_c).atan2(rho * phi0.cos() * cos_c - y * phi0.sin() * sin_c);
+
+        Some((wrap_longitude(self.center.longitude + radians_to_degrees(&dlambda)),
+              radians_to_degrees(&phi)))
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn wraps_longitude(&self) -> bool {
+        false
+    }
+
+    /// Near the antipode the map turns inside out: a track passing it sweeps around the rim,
+    /// so two far side points more than MAX_FAR_SIDE_TURN of bearing apart are not joined across the disc.
+    fn breaks_between(&self, from: &Geodetic, to: &Geodetic) -> bool {
+        let (from_angle, from_bearing) = self.polar(from.longitude, from.latitude);
+        let (to_angle, to_bearing) = self.polar(to.longitude, to.latitude);
+        let turn = (to_bearing - from_bearing).abs();
+        let turn = turn.min(2.0 * core::f64::consts::PI - turn);
+        from_angle > core::f64::consts::FRAC_PI_2 && to_angle > core::f64::consts::FRAC_PI_2
+            && turn > degrees_to_radians(&MAX_FAR_SIDE_TURN)
+    }
+}
+
+/// reproject_map: redraws an equirectangular map (e.g. BigEarth.jpg) in another projection.
+/// Pixels outside the projected earth are left transparent.
+/// input: equirectangular source image, target projection
+/// output: new image with the target projection's dimensions
+pub fn reproject_map(source: &image::RgbaImage, projection: &dyn Projection) -> image::RgbaImage {
+    let (source_width, source_height) = source.dimensions();
+    let source_projection = Equirectangular { width: source_width, height: source_height };
+    let (width, height) = projection.dimensions();
+    let mut output: image::RgbaImage = image::ImageBuffer::new(width, height);
+
+    for (x, y, pixel) in output.enumerate_pixels_mut() {
+        let lon_lat = projection.inverse(x as f64 + 0.5, y as f64 + 0.5);
+        let source_xy = lon_lat.and_then(|(lon, lat)| source_projection.forward(lon, lat));
+        if let Some((sx, sy)) = source_xy {
+            let sx = (sx.floor() as u32).min(source_width - 1);
+            let sy = (sy.floor() as u32).min(source_height - 1);
+            *pixel = *source.get_pixel(sx, sy);
+        }
+    }
+    output
+}
+
+/// wrap_longitude: longitude wrapped to [-180, 180).
+pub fn wrap_longitude(longitude: f64) -> f64 {
+    (longitude + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// edge_longitude: like wrap_longitude but keeps +180 so lines can reach the right edge.
+fn edge_longitude(longitude: f64) -> f64 {
+    if (-180.0..=180.0).contains(&longitude) { longitude } else { wrap_longitude(longitude) }
+}
+
+fn in_image(x: f64, y: f64, width: u32, height: u32) -> bool {
+    x >= 0.0 && y >= 0.0 && x <= width as f64 && y <= height as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_round_trip(projection: &dyn Projection, longitude: f64, latitude: f64) {
+        let (x, y) = projection.forward(longitude, latitude)
+            .unwrap_or_else(|| panic!("({}, {}) not projected", longitude, latitude));
+        let (lon, lat) = projection.inverse(x, y)
+            .unwrap_or_else(|| panic!("({}, {}) not inverted", x, y));
+        assert!((lat - latitude).abs() < 1e-9, "latitude {} -> {}", latitude, lat);
+        assert!((wrap_longitude(lon - longitude)).abs() < 1e-9, "longitude {} -> {}", longitude, lon);
+    }
+
+    const POINTS: [(f64, f64); 7] = [
+        (0.0, 0.0),
+        (-112.074, 33.4484),
+        (151.2, -33.9),
+        (179.9, 10.0),
+        (-179.9, -10.0),
+        (45.0, 80.0),
+        (-60.0, -75.0),
+    ];
+
+    #[test]
+    fn equirectangular_round_trip() {
+        let projection = Equirectangular { width: 1024, height: 512 };
+        for (lon, lat) in POINTS {
+            assert_round_trip(&projection, lon, lat);
+        }
+        assert_eq!(projection.forward(0.0, 0.0), Some((512.0, 256.0)));
+        // x follows longitude and y follows latitude.
+        assert_eq!(projection.forward(-180.0, 90.0), Some((0.0, 0.0)));
+        assert_eq!(projection.forward(90.0, -45.0), Some((768.0, 384.0)));
+    }
+
+    #[test]
+    fn web_mercator_round_trip() {
+        let projection = WebMercator { width: 1024, height: 1024 };
+        for (lon, lat) in POINTS {
+            assert_round_trip(&projection, lon, lat);
+        }
+        assert_eq!(projection.forward(0.0, 0.0), Some((512.0, 512.0)));
+        let (_, top) = projection.forward(0.0, WEB_MERCATOR_MAX_LATITUDE).unwrap();
+        assert!(top.abs() < 1e-6);
+        assert!(projection.forward(0.0, 89.0).is_none());
+    }
+
+    #[test]
+    fn azimuthal_equidistant_round_trip() {
+        let gateway = Geodetic { latitude: 33.4484, longitude: -112.0740, altitude: 0.0 };
+        let projection = AzimuthalEquidistant { width: 800, height: 800, center: gateway };
+        for (lon, lat) in POINTS {
+            assert_round_trip(&projection, lon, lat);
+        }
+        let (x, y) = projection.forward(gateway.longitude, gateway.latitude).unwrap();
+        assert!((x - 400.0).abs() < 1e-9 && (y - 400.0).abs() < 1e-9);
+
+        // Due north of the centre is straight up, distance proportional to the central angle.
+        let (x, y) = projection.forward(gateway.longitude, gateway.latitude + 30.0).unwrap();
+        assert!((x - 400.0).abs() < 1e-9);
+        assert!((400.0 - y - 400.0 / 6.0).abs() < 1e-9);
+        assert!(projection.inverse(0.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn azimuthal_tracks_break_past_the_antipode() {
+        let point = |longitude, latitude| Geodetic { latitude, longitude, altitude: 0.0 };
+        let projection = AzimuthalEquidistant { width: 800, height: 800, center: point(0.0, 0.0) };
+        // Either side of the antipode (180, 0) lands on opposite sides of the rim.
+        assert!(projection.breaks_between(&point(179.0, 1.0), &point(-179.0, 1.0)));
+        assert!(projection.breaks_between(&point(178.0, 1.0), &point(178.0, -1.0)));
+        assert!(!projection.breaks_between(&point(170.0, 1.0), &point(172.0, 1.0)));
+        assert!(!projection.breaks_between(&point(-1.0, 0.0), &point(1.0, 0.0)));
+        assert!(!Equirectangular { width: 360, height: 180 }.breaks_between(&point(179.0, 1.0), &point(-179.0, 1.0)));
+    }
+}
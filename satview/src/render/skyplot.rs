@@ -0,0 +1,248 @@
+//! purpose:
+//!     skyplot.rs renders a polar sky plot for an observer, azimuth around the circle
+//!     (north up, east right) and elevation from 0 degrees at the rim to 90 at the centre.
+//!     Every pass is drawn horizon to horizon, the part below the observer's elevation mask
+//!     is dimmed and the masked band is shaded so the usable part of each pass stands out.
+
+use chrono::{NaiveDateTime, TimeDelta};
+use crate::coordinate_systems::LookAngle;
+use crate::obs::observer::Observer;
+use crate::render::draw::{blend_pixel, draw_circle_aa, draw_line_aa};
+use crate::render::font::{draw_text, draw_text_centered};
+use crate::render::groundtrack::track_color;
+use crate::sat::satellite::Satellite;
+use crate::utils::satutils::degrees_to_radians;
+use crate::vis::passes::{find_passes, PassConfig, PassWindow};
+
+const BACKGROUND: [u8; 4] = [12, 16, 32, 255];
+const GRID_COLOR: [u8; 4] = [110, 120, 140, 255];
+const LABEL_COLOR: [u8; 4] = [220, 220, 220, 255];
+const MASK_COLOR: [u8; 4] = [255, 60, 60, 255];
+const MASK_SHADE: [u8; 4] = [255, 60, 60, 48];
+/// Alpha of the part of a pass that is below the elevation mask.
+const BELOW_MASK_ALPHA: u8 = 90;
+
+/// Settings for draw_sky_plot.
+/// size is the width and height of the image in pixels.
+/// step is the sampling interval along each pass.
+#[derive(Debug, Copy, Clone)]
+pub struct SkyPlotConfig {
+    pub size: u32,
+    pub step: TimeDelta,
+}
+
+impl Default for SkyPlotConfig {
+    fn default() -> SkyPlotConfig {
+        SkyPlotConfig {
+            size: 800,
+            step: TimeDelta::seconds(10),
+        }
+    }
+}
+
+/// sky_path: look angles sampled along a pass, from AOS to LOS.
+/// input: observer, satellite, pass window, step
+/// output: look angles (degrees, km, km/s), the last one is at LOS
+pub fn sky_path(observer: &Observer,
+                satellite: &mut Satellite,
+                window: &PassWindow,
+                step: &TimeDelta) -> anyhow::Result<Vec<LookAngle>> {
+    if *step <= TimeDelta::zero() {
+        anyhow::bail!("sky plot step must be positive");
+    }
+    let mut observer = *observer;
+    let mut path = Vec::new();
+    let mut time = window.aos;
+    loop {
+        satellite.propagate(&time)?;
+        path.push(observer.look_angle_to(satellite));
+        if time >= window.los {
+            break;
+        }
+        time = (time + *step).min(window.los);
+    }
+    Ok(path)
+}
+
+/// sky_point: pixel position of an azimuth/elevation on the plot.
+/// input: azimuth and elevation in degrees, plot centre, radius of the horizon circle in pixels
+/// output: (x, y), elevations below the horizon are drawn on the horizon
+pub fn sky_point(azimuth: &f64, elevation: &f64, center: (f64, f64), radius: f64) -> (f64, f64) {
+    let distance = radius * (90.0 - elevation.clamp(0.0, 90.0)) / 90.0;
+    let azimuth = degrees_to_radians(azimuth);
+    (center.0 + distance * azimuth.sin(), center.1 - distance * azimuth.cos())
+}
+
+/// draw_sky_plot: sky plot of every pass of the satellites over the observer between start and end.
+/// Satellites are coloured by index, as on the ground track map, and labelled with the
+/// 3LE name line at culmination. The start of each pass (AOS) is marked with a dot.
+/// input: observer, satellites, start and end (UTC), plot config
+/// output: the plot image
+pub fn draw_sky_plot(observer: &Observer,
+                     satellites: &mut [Satellite],
+                     start: &NaiveDateTime,
+                     end: &NaiveDateTime,
+                     config: &SkyPlotConfig) -> anyhow::Result<image::RgbaImage> {
+    if config.size < 100 {
+        anyhow::bail!("sky plot size {} is too small, use at least 100 pixels", config.size);
+    }
+    let mut image = image::RgbaImage::from_pixel(config.size, config.size, image::Rgba(BACKGROUND));
+    let center = (config.size as f64 / 2.0, config.size as f64 / 2.0);
+    let radius = config.size as f64 / 2.0 - config.size as f64 * 0.08;
+    let scale = (config.size / 400).max(1);
+
+    shade_elevation_mask(&mut image, center, radius, &observer.elevation_mask);
+    draw_sky_grid(&mut image, center, radius, scale);
+    let mask_radius = radius * (90.0 - observer.elevation_mask.clamp(0.0, 90.0)) / 90.0;
+    draw_circle_aa(&mut image, center, mask_radius, &MASK_COLOR);
+
+    // Horizon to horizon passes, the mask only changes how they are drawn.
+    let horizon = PassConfig {
+        min_elevation: 0.0,
+        ..Default::default()
+    };
+    let horizon_observer = Observer { elevation_mask: 0.0, ..*observer };
+    for (index, satellite) in satellites.iter_mut().enumerate() {
+        let color = track_color(index);
+        let dim = [color[0], color[1], color[2], BELOW_MASK_ALPHA];
+        let name = satellite.name();
+
+        for window in find_passes(&horizon_observer, satellite, start, end, &horizon)? {
+            let path = sky_path(observer, satellite, &window, &config.step)?;
+            for pair in path.windows(2) {
+                let visible = pair[0].elevation >= observer.elevation_mask
+                    && pair[1].elevation >= observer.elevation_mask;
+                draw_line_aa(&mut image,
+                             sky_point(&pair[0].azimuth, &pair[0].elevation, center, radius),
+                             sky_point(&pair[1].azimuth, &pair[1].elevation, center, radius),
+                             if visible { &color } else { &dim });
+            }
+            let aos = sky_point(&path[0].azimuth, &path[0].elevation, center, radius);
+            draw_dot(&mut image, aos, 2.5 * scale as f64, &color);
+            let (x, y) = sky_point(&window.max_elevation_azimuth, &window.max_elevation, center, radius);
+            draw_text_centered(&mut image, x, y - 8.0 * scale as f64, &name, &color, scale);
+        }
+    }
+
+    let latitude = &observer.geodetic_coordinates.latitude;
+    let longitude = &observer.geodetic_coordinates.longitude;
+    draw_text(&mut image, 4, 4, &format!("LAT {:.4} LON {:.4}", latitude, longitude), &LABEL_COLOR, scale);
+    let range = format!("{} - {} UTC", start.format("%Y-%m-%d %H:%M"), end.format("%Y-%m-%d %H:%M"));
+    let bottom = config.size as i64 - 4 - 7 * scale as i64;
+    draw_text(&mut image, 4, bottom, &range, &LABEL_COLOR, scale);
+    Ok(image)
+}
+
+/// draw_sky_grid: elevation rings every 30 degrees, azimuth spokes every 30 degrees and their labels.
+fn draw_sky_grid(image: &mut image::RgbaImage, center: (f64, f64), radius: f64, scale: u32) {
+    for elevation in [0.0, 30.0, 60.0] {
+        draw_circle_aa(image, center, radius * (90.0 - elevation) / 90.0, &GRID_COLOR);
+        if elevation > 0.0 {
+            let (x, y) = sky_point(&0.0, &elevation, center, radius);
+            draw_text(image, x as i64 + 3, y as i64 + 2, &format!("{}", elevation), &GRID_COLOR, scale);
+        }
+    }
+    for step in 0..12 {
+        let azimuth = step as f64 * 30.0;
+        draw_line_aa(image, center, sky_point(&azimuth, &0.0, center, radius), &GRID_COLOR);
+        let label = match step {
+            0 => "N".to_string(),
+            3 => "E".to_string(),
+            6 => "S".to_string(),
+            9 => "W".to_string(),
+            _ => format!("{}", azimuth),
+        };
+        let (x, y) = sky_point(&azimuth, &0.0, center, radius + 12.0 * scale as f64);
+        draw_text_centered(image, x, y, &label, &LABEL_COLOR, scale);
+    }
+}
+
+/// shade_elevation_mask: shades the band between the horizon and the elevation mask.
+fn shade_elevation_mask(image: &mut image::RgbaImage, center: (f64, f64), radius: f64, elevation_mask: &f64) {
+    let inner = radius * (90.0 - elevation_mask.clamp(0.0, 90.0)) / 90.0;
+    let (width, height) = image.dimensions();
+    for y in 0..height {
+        for x in 0..width {
+            let distance = (x as f64 + 0.5 - center.0).hypot(y as f64 + 0.5 - center.1);
+            if distance > inner && distance <= radius {
+                blend_pixel(image, x as i64, y as i64, &MASK_SHADE, 1.0);
+            }
+        }
+    }
+}
+
+/// draw_dot: filled circle with an anti-aliased edge.
+fn draw_dot(image: &mut image::RgbaImage, center: (f64, f64), radius: f64, color: &[u8; 4]) {
+    let reach = radius.ceil() as i64 + 1;
+    let (cx, cy) = (center.0.round() as i64, center.1.round() as i64);
+    for y in cy - reach..=cy + reach {
+        for x in cx - reach..=cx + reach {
+            let distance = (x as f64 + 0.5 - center.0).hypot(y as f64 + 0.5 - center.1);
+            blend_pixel(image, x, y, color, radius + 0.5 - distance);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::sat::tle::parse_tles;
+
+    fn close(a: (f64, f64), b: (f64, f64)) -> bool {
+        (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9
+    }
+
+    #[test]
+    fn zenith_is_the_centre_and_north_is_up() {
+        let center = (200.0, 200.0);
+        assert!(close(sky_point(&123.0, &90.0, center, 150.0), center));
+        assert!(close(sky_point(&0.0, &0.0, center, 150.0), (200.0, 50.0)));
+        assert!(close(sky_point(&90.0, &0.0, center, 150.0), (350.0, 200.0)));
+        assert!(close(sky_point(&180.0, &45.0, center, 150.0), (200.0, 275.0)));
+        assert!(close(sky_point(&270.0, &30.0, center, 150.0), (100.0, 200.0)));
+        // Below the horizon stays on the rim.
+        assert!(close(sky_point(&90.0, &-5.0, center, 150.0), (350.0, 200.0)));
+    }
+
+    #[test]
+    fn passes_are_drawn_above_the_horizon_and_dimmed_below_the_mask() {
+        let mut satellite = Satellite::new(parse_tles(include_str!("../../common/tle2.txt")).elements.remove(0));
+        let mut observer = Observer::from_coordinates([33.4484, -112.0740, 0.331]);
+        observer.elevation_mask = 20.0;
+        let start = NaiveDate::from_ymd_opt(2012, 10, 15).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let end = start + TimeDelta::days(1);
+        let horizon = Observer { elevation_mask: 0.0, ..observer };
+        let config = SkyPlotConfig::default();
+        let window = find_passes(&horizon, &mut satellite, &start, &end, &PassConfig { min_elevation: 0.0, ..Default::default() })
+            .unwrap()
+            .into_iter()
+            .max_by(|a, b| a.max_elevation.total_cmp(&b.max_elevation))
+            .unwrap();
+
+        let path = sky_path(&observer, &mut satellite, &window, &config.step).unwrap();
+        assert_eq!(path.len() as i64, (window.duration().num_milliseconds() + 9999) / 10_000 + 1);
+        assert!(path.iter().all(|look| look.elevation >= -1e-6));
+        assert!(path[0].elevation < 0.01 && path[path.len() - 1].elevation < 0.01);
+        assert!(path.iter().any(|look| look.elevation > observer.elevation_mask));
+
+        let image = draw_sky_plot(&observer, std::slice::from_mut(&mut satellite), &start, &end, &config).unwrap();
+        // The first track is gold, strong in green and without blue, unlike the background, grid and mask shade.
+        // Full gold is 215 green over blue, a dimmed line stays below 140 even where two segments meet.
+        let gold = |look: &LookAngle| {
+            let (x, y) = sky_point(&look.azimuth, &look.elevation, (400.0, 400.0), 400.0 - 64.0);
+            let (x, y) = (x.round() as i64, y.round() as i64);
+            (x - 1..=x + 1).flat_map(|x| (y - 1..=y + 1).map(move |y| (x, y)))
+                .map(|(x, y)| image.get_pixel(x as u32, y as u32).0)
+                .map(|pixel| pixel[1] as i64 - pixel[2] as i64)
+                .max()
+                .unwrap()
+        };
+        for look in path.iter().filter(|look| (5.0..15.0).contains(&look.elevation)) {
+            assert!(gold(look) < 140, "{} at {:?}", gold(look), look);
+        }
+        for look in path.iter().filter(|look| look.elevation > 25.0) {
+            assert!(gold(look) > 150, "{} at {:?}", gold(look), look);
+        }
+    }
+}
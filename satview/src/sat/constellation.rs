@@ -0,0 +1,173 @@
+//! purpose:
+//!     constellation.rs generates whole constellations of circular orbits for expansion studies:
+//!     Walker-delta (planes spread over 360 degrees of RAAN), Walker-star (spread over 180 degrees)
+//!     and single-plane rings like the equatorial MP fleet in common/tle.txt.
+//!     Walker notation i:t/p/f is t satellites in p equally spaced planes at inclination i, with
+//!     satellites of neighbouring planes phased by f * 360 / t degrees.
+//!     The result is a list of KeplerianElements, ready for Satellite or for a 3LE file (tle::write_tles).
+
+use chrono::NaiveDateTime;
+use crate::sat::keplerian::KeplerianElements;
+use crate::sat::satellite::Satellite;
+
+/// How the orbital planes are laid out.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ConstellationPattern {
+    WalkerDelta,
+    WalkerStar,
+    /// One plane, planes and phasing are ignored.
+    Ring,
+}
+
+/// Constellation layout and naming. Altitude in km of the sgp4 mean semi-major axis above the WGS-72
+/// equatorial radius (see keplerian.rs), angles in degrees.
+/// satellites is the total t and must divide evenly over the planes p, phasing f runs from 0 to p - 1.
+/// raan is the right ascension of the first plane, mean_anomaly the position of its first satellite.
+/// Satellites are named name_prefix plus a running number (MP01, MP02, ...), numbered from first_norad_id,
+/// and get designator pieces A, B, ... after launch (YYYY-NNN) when one is given.
+#[derive(Debug, Clone)]
+pub struct ConstellationConfig {
+    pub pattern: ConstellationPattern,
+    pub satellites: u32,
+    pub planes: u32,
+    pub phasing: u32,
+    pub altitude: f64,
+    pub inclination: f64,
+    pub raan: f64,
+    pub mean_anomaly: f64,
+    pub name_prefix: String,
+    pub first_norad_id: u64,
+    pub launch: Option<String>,
+}
+
+/// Defaults to a ring like the MP fleet: six satellites on the equator at about 5 rev/day.
+impl Default for ConstellationConfig {
+    fn default() -> ConstellationConfig {
+        ConstellationConfig {
+            pattern: ConstellationPattern::Ring,
+            satellites: 6,
+            planes: 1,
+            phasing: 0,
+            altitude: 8067.0,
+            inclination: 0.0,
+            raan: 0.0,
+            mean_anomaly: 0.0,
+            name_prefix: "MP".to_string(),
+            first_norad_id: 99901,
+            launch: Some("2022-999".to_string()),
+        }
+    }
+}
+
+/// generate_constellation: elements of every satellite of a constellation at an epoch (UTC),
+/// plane by plane in order of RAAN, satellites within a plane in order of mean anomaly.
+pub fn generate_constellation(config: &ConstellationConfig,
+                              epoch: &NaiveDateTime) -> anyhow::Result<Vec<KeplerianElements>> {
+    let planes = match config.pattern {
+        ConstellationPattern::Ring => 1,
+        _ => config.planes,
+    };
+    let phasing = match config.pattern {
+        ConstellationPattern::Ring => 0,
+        _ => config.phasing,
+    };
+    if config.satellites == 0 || planes == 0 {
+        anyhow::bail!("a constellation needs at least one satellite and one plane");
+    }
+    if !config.satellites.is_multiple_of(planes) {
+        anyhow::bail!("{} satellites do not divide evenly over {} planes", config.satellites, planes);
+    }
+    if phasing >= planes {
+        anyhow::bail!("phasing {} must be less than the number of planes {}", phasing, planes);
+    }
+    let raan_spread = match config.pattern {
+        ConstellationPattern::WalkerStar => 180.0,
+        _ => 360.0,
+    };
+
+    let per_plane = config.satellites / planes;
+    let digits = config.satellites.to_string().len().max(2);
+    let mut elements = Vec::with_capacity(config.satellites as usize);
+    for plane in 0..planes {
+        for slot in 0..per_plane {
+            let index = plane * per_plane + slot;
+            let mean_anomaly = config.mean_anomaly
+                + 360.0 * slot as f64 / per_plane as f64
+                + 360.0 * (phasing * plane) as f64 / config.satellites as f64;
+            elements.push(KeplerianElements {
+                name: format!("{}{:0width$}", config.name_prefix, index + 1, width = digits),
+                norad_id: config.first_norad_id + index as u64,
+                international_designator: config.launch.as_ref().map(|launch| format!("{}{}", launch, launch_piece(index))),
+                epoch: *epoch,
+                semi_major_axis: sgp4::WGS72.ae + config.altitude,
+                eccentricity: 0.0,
+                inclination: config.inclination,
+                right_ascension: config.raan + raan_spread * plane as f64 / planes as f64,
+                argument_of_perigee: 0.0,
+                mean_anomaly,
+                drag_term: 0.0,
+            });
+        }
+    }
+    Ok(elements)
+}
+
+/// constellation_satellites: satellites of a constellation, propagated with sgp4.
+pub fn constellation_satellites(config: &ConstellationConfig,
+                                epoch: &NaiveDateTime) -> anyhow::Result<Vec<Satellite>> {
+    generate_constellation(config, epoch)?
+        .iter()
+        .map(KeplerianElements::to_satellite)
+        .collect()
+}
+
+/// launch_piece: piece letters of the n-th object of a launch, A to Z, then AA, AB, ... (I and O are skipped).
+fn launch_piece(index: u32) -> String {
+    const LETTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+    let base = LETTERS.len() as u32;
+    let mut index = index;
+    let mut piece = vec![LETTERS[(index % base) as usize]];
+    while index >= base {
+        index = index / base - 1;
+        piece.push(LETTERS[(index % base) as usize]);
+    }
+    piece.iter().rev().map(|&letter| letter as char).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn walker_delta_layout() {
+        // Galileo-like 56:24/3/1.
+        let config = ConstellationConfig {
+            pattern: ConstellationPattern::WalkerDelta,
+            satellites: 24,
+            planes: 3,
+            phasing: 1,
+            altitude: 23_222.0,
+            inclination: 56.0,
+            ..Default::default()
+        };
+        let epoch = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let elements = generate_constellation(&config, &epoch).unwrap();
+        assert_eq!(elements.len(), 24);
+        assert_eq!(elements[0].name, "MP01");
+        assert_eq!(elements[23].name, "MP24");
+        assert_eq!(elements[23].norad_id, 99924);
+        assert_eq!(elements[8].international_designator.as_deref(), Some("2022-999J"));
+        // Eight per plane, 45 degrees apart, next plane 120 degrees on and 15 degrees ahead.
+        assert_eq!(elements[1].mean_anomaly, 45.0);
+        assert_eq!(elements[8].right_ascension, 120.0);
+        assert_eq!(elements[8].mean_anomaly, 15.0);
+        assert_eq!(elements[16].right_ascension, 240.0);
+        assert_eq!(elements[16].mean_anomaly, 30.0);
+
+        let star = ConstellationConfig { pattern: ConstellationPattern::WalkerStar, ..config.clone() };
+        assert_eq!(generate_constellation(&star, &epoch).unwrap()[16].right_ascension, 120.0);
+        let uneven = ConstellationConfig { satellites: 25, ..config };
+        assert!(generate_constellation(&uneven, &epoch).is_err());
+    }
+}
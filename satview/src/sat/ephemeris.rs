@@ -0,0 +1,351 @@
+//! purpose:
+//!     ephemeris.rs holds tabulated satellite states, the operator precise ephemerides read from
+//!     CCSDS OEM (oem.rs) or SP3 (sp3.rs) files, and interpolates them to any time inside their span.
+//!     Samples are converted to UTC and TEME when loaded, the time scale and frame sgp4 predicts in,
+//!     so an ephemeris satellite goes through the same coordinate pipeline as a TLE one.
+//!     Lagrange interpolation fits the positions (the velocity is the derivative of the fit),
+//!     Hermite also matches the sampled velocities and needs fewer points for the same accuracy.
+
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+use sgp4::Prediction;
+use crate::coordinate_systems::ECEF;
+use crate::sat::propagator::osculating_period;
+use crate::utils::satutils::{ecef_to_teme, j2000_to_teme};
+
+/// Default number of samples used per Lagrange interpolation (degree 7).
+pub const DEFAULT_LAGRANGE_POINTS: usize = 8;
+/// Default number of samples used per Hermite interpolation (degree 7).
+pub const DEFAULT_HERMITE_POINTS: usize = 4;
+/// How far outside its span an ephemeris may be extrapolated, seconds.
+/// Enough for the Doppler rate difference at the first and last sample.
+const EXTRAPOLATION_LIMIT: f64 = 10.0;
+
+/// Interpolation method of an ephemeris.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Interpolation {
+    Lagrange,
+    Hermite,
+}
+
+impl Interpolation {
+    /// points_for_degree: samples needed for a polynomial of this degree,
+    /// degree + 1 for Lagrange, (degree + 1) / 2 for Hermite.
+    pub fn points_for_degree(&self, degree: usize) -> usize {
+        match self {
+            Interpolation::Lagrange => degree + 1,
+            Interpolation::Hermite => degree.div_ceil(2),
+        }
+    }
+}
+
+/// Time scale of the epochs in an ephemeris file.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TimeSystem {
+    Utc,
+    Tai,
+    Tt,
+    Gps,
+    BeiDou,
+}
+
+/// TAI - UTC in seconds from each date on (IERS Bulletin C, no leap second since 2017).
+const LEAP_SECONDS: [(i32, u32, u32, i64); 28] = [
+    (1972, 1, 1, 10), (1972, 7, 1, 11), (1973, 1, 1, 12), (1974, 1, 1, 13), (1975, 1, 1, 14),
+    (1976, 1, 1, 15), (1977, 1, 1, 16), (1978, 1, 1, 17), (1979, 1, 1, 18), (1980, 1, 1, 19),
+    (1981, 7, 1, 20), (1982, 7, 1, 21), (1983, 7, 1, 22), (1985, 7, 1, 23), (1988, 1, 1, 24),
+    (1990, 1, 1, 25), (1991, 1, 1, 26), (1992, 7, 1, 27), (1993, 7, 1, 28), (1994, 7, 1, 29),
+    (1996, 1, 1, 30), (1997, 7, 1, 31), (1999, 1, 1, 32), (2006, 1, 1, 33), (2009, 1, 1, 34),
+    (2012, 7, 1, 35), (2015, 7, 1, 36), (2017, 1, 1, 37),
+];
+
+impl TimeSystem {
+    /// parse: time system from its OEM TIME_SYSTEM or SP3 name. Galileo and QZSS time follow GPS time.
+    pub fn parse(name: &str) -> anyhow::Result<TimeSystem> {
+        match name.trim().to_ascii_uppercase().as_str() {
+            "UTC" => Ok(TimeSystem::Utc),
+            "TAI" => Ok(TimeSystem::Tai),
+            "TT" | "TDT" => Ok(TimeSystem::Tt),
+            "GPS" | "GAL" | "GST" | "QZS" => Ok(TimeSystem::Gps),
+            "BDT" => Ok(TimeSystem::BeiDou),
+            other => anyhow::bail!("unsupported time system {}", other),
+        }
+    }
+
+    /// to_utc: a time of this scale as UTC.
+    pub fn to_utc(&self, time: &NaiveDateTime) -> NaiveDateTime {
+        let tai = match self {
+            TimeSystem::Utc => return *time,
+            TimeSystem::Tai => *time,
+            TimeSystem::Tt => *time - TimeDelta::milliseconds(32_184),
+            TimeSystem::Gps => *time + TimeDelta::seconds(19),
+            TimeSystem::BeiDou => *time + TimeDelta::seconds(33),
+        };
+        for (year, month, day, offset) in LEAP_SECONDS.iter().rev() {
+            let utc = tai - TimeDelta::seconds(*offset);
+            if utc >= NaiveDate::from_ymd_opt(*year, *month, *day).unwrap().and_hms_opt(0, 0, 0).unwrap() {
+                return utc;
+            }
+        }
+        tai - TimeDelta::seconds(10)
+    }
+}
+
+/// Reference frame of the states in an ephemeris file.
+/// EarthFixed covers ITRF and its realisations (IGS, WGS-84), polar motion is ignored as elsewhere.
+/// J2000 covers EME2000, GCRF and ICRF, the frame bias between them is ignored.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ReferenceFrame {
+    Teme,
+    EarthFixed,
+    J2000,
+}
+
+impl ReferenceFrame {
+    /// parse: frame from its OEM REF_FRAME or SP3 coordinate system name.
+    pub fn parse(name: &str) -> anyhow::Result<ReferenceFrame> {
+        let name = name.trim().to_ascii_uppercase();
+        match name.as_str() {
+            "TEME" => Ok(ReferenceFrame::Teme),
+            "EME2000" | "J2000" | "GCRF" | "ICRF" => Ok(ReferenceFrame::J2000),
+            _ if ["ITRF", "IGS", "IGB", "WGS84", "WGS-84", "ECEF"].iter()
+                .any(|prefix| name.starts_with(prefix)) => Ok(ReferenceFrame::EarthFixed),
+            _ => anyhow::bail!("unsupported reference frame {}", name),
+        }
+    }
+
+    /// to_teme: a state in this frame at a UTC time, in TEME. A missing velocity stays missing.
+    pub fn to_teme(&self,
+                   time: &NaiveDateTime,
+                   position: [f64; 3],
+                   velocity: Option<[f64; 3]>) -> ([f64; 3], Option<[f64; 3]>) {
+        match self {
+            ReferenceFrame::Teme => (position, velocity),
+            ReferenceFrame::EarthFixed => {
+                let ecef = ECEF { x: position[0], y: position[1], z: position[2] };
+                let teme = ecef_to_teme(&ecef, &velocity.unwrap_or([0.0; 3]), time);
+                (teme.pos_vector, velocity.map(|_| teme.velo_vector))
+            }
+            ReferenceFrame::J2000 => {
+                let (position, teme_velocity) = j2000_to_teme(&position, &velocity.unwrap_or([0.0; 3]), time);
+                (position, velocity.map(|_| teme_velocity))
+            }
+        }
+    }
+}
+
+/// One tabulated state: UTC time, TEME position in km and, when the file has it, velocity in km/s.
+#[derive(Debug, Copy, Clone)]
+pub struct StateSample {
+    pub time: NaiveDateTime,
+    pub position: [f64; 3],
+    pub velocity: Option<[f64; 3]>,
+}
+
+/// A tabulated ephemeris of one satellite, samples in time order.
+/// points is the number of samples around the requested time each interpolation uses.
+#[derive(Debug, Clone)]
+pub struct Ephemeris {
+    pub name: String,
+    samples: Vec<StateSample>,
+    interpolation: Interpolation,
+    points: usize,
+}
+
+impl Ephemeris {
+    /// new: an ephemeris from samples in any order, duplicate times are dropped.
+    /// Fails with fewer than two samples, or for Hermite interpolation when a sample has no velocity.
+    pub fn new(name: &str,
+               mut samples: Vec<StateSample>,
+               interpolation: Interpolation,
+               points: usize) -> anyhow::Result<Ephemeris> {
+        samples.sort_by_key(|sample| sample.time);
+        samples.dedup_by_key(|sample| sample.time);
+        if samples.len() < 2 {
+            anyhow::bail!("ephemeris of {} has {} samples, at least 2 are needed", name, samples.len());
+        }
+        let mut ephemeris = Ephemeris { name: name.to_string(), samples, interpolation, points };
+        ephemeris.set_interpolation(interpolation, points)?;
+        Ok(ephemeris)
+    }
+
+    /// set_interpolation: changes the interpolation method and number of points,
+    /// capped at the number of samples.
+    pub fn set_interpolation(&mut self, interpolation: Interpolation, points: usize) -> anyhow::Result<()> {
+        if points < 2 {
+            anyhow::bail!("interpolation needs at least 2 points, got {}", points);
+        }
+        if interpolation == Interpolation::Hermite && self.samples.iter().any(|sample| sample.velocity.is_none()) {
+            anyhow::bail!("Hermite interpolation of {} needs velocities, the ephemeris has positions only", self.name);
+        }
+        self.interpolation = interpolation;
+        self.points = points.min(self.samples.len());
+        Ok(())
+    }
+
+    pub fn interpolation(&self) -> (Interpolation, usize) {
+        (self.interpolation, self.points)
+    }
+
+    pub fn samples(&self) -> &[StateSample] {
+        &self.samples
+    }
+
+    pub fn start(&self) -> NaiveDateTime {
+        self.samples[0].time
+    }
+
+    pub fn end(&self) -> NaiveDateTime {
+        self.samples[self.samples.len() - 1].time
+    }
+
+    /// orbital_period: two-body period of the osculating orbit at the first sample.
+    pub fn orbital_period(&self) -> anyhow::Result<TimeDelta> {
+        let state = self.state_at(&self.start())?;
+        osculating_period(&state.position, &state.velocity)
+            .map_err(|err| anyhow::anyhow!("{}: {}", self.name, err))
+    }
+
+    /// state_at: interpolated TEME position (km) and velocity (km/s) at a UTC time.
+    /// Fails outside the span of the samples (give or take EXTRAPOLATION_LIMIT).
+    pub fn state_at(&self, time: &NaiveDateTime) -> anyhow::Result<Prediction> {
+        let limit = TimeDelta::milliseconds((EXTRAPOLATION_LIMIT * 1000.0) as i64);
+        if *time < self.start() - limit || *time > self.end() + limit {
+            anyhow::bail!("{} ephemeris covers {} to {}, {} is outside it", self.name, self.start(), self.end(), time);
+        }
+
+        // The window of samples centred on the requested time.
+        let after = self.samples.partition_point(|sample| sample.time <= *time);
+        let first = after.saturating_sub(self.points / 2).min(self.samples.len() - self.points);
+        let window = &self.samples[first..first + self.points];
+        // Times in seconds from the middle of the window, for conditioning.
+        let reference = window[window.len() / 2].time;
+        let seconds = |t: &NaiveDateTime| (*t - reference).num_nanoseconds().unwrap_or(0) as f64 / 1e9;
+        let nodes: Vec<f64> = window.iter().map(|sample| seconds(&sample.time)).collect();
+        let t = seconds(time);
+
+        let mut position = [0.0; 3];
+        let mut velocity = [0.0; 3];
+        match self.interpolation {
+            Interpolation::Lagrange => {
+                let (basis, derivative) = lagrange_basis(&nodes, t);
+                for (i, sample) in window.iter().enumerate() {
+                    for axis in 0..3 {
+                        position[axis] += basis[i] * sample.position[axis];
+                        velocity[axis] += derivative[i] * sample.position[axis];
+                    }
+                }
+            }
+            Interpolation::Hermite => {
+                let (basis, derivative) = lagrange_basis(&nodes, t);
+                for (i, sample) in window.iter().enumerate() {
+                    let sample_velocity = sample.velocity.unwrap_or([0.0; 3]);
+                    // Slope of the i-th Lagrange basis at its own node.
+                    let slope: f64 = nodes.iter().enumerate()
+                        .filter(|(j, _)| *j != i)
+                        .map(|(_, node)| 1.0 / (nodes[i] - node))
+                        .sum();
+                    let dt = t - nodes[i];
+                    let squared = basis[i] * basis[i];
+                    let squared_derivative = 2.0 * basis[i] * derivative[i];
+                    let h = (1.0 - 2.0 * slope * dt) * squared;
+                    let h_derivative = -2.0 * slope * squared + (1.0 - 2.0 * slope * dt) * squared_derivative;
+                    let g = dt * squared;
+                    let g_derivative = squared + dt * squared_derivative;
+                    for axis in 0..3 {
+                        position[axis] += h * sample.position[axis] + g * sample_velocity[axis];
+                        velocity[axis] += h_derivative * sample.position[axis] + g_derivative * sample_velocity[axis];
+                    }
+                }
+            }
+        }
+        Ok(Prediction { position, velocity })
+    }
+}
+
+/// lagrange_basis: values and derivatives of the Lagrange basis polynomials of the nodes at t.
+fn lagrange_basis(nodes: &[f64], t: f64) -> (Vec<f64>, Vec<f64>) {
+    let n = nodes.len();
+    let mut values = vec![0.0; n];
+    let mut derivatives = vec![0.0; n];
+    for i in 0..n {
+        let mut value = 1.0;
+        for j in (0..n).filter(|&j| j != i) {
+            value *= (t - nodes[j]) / (nodes[i] - nodes[j]);
+        }
+        values[i] = value;
+        // Product rule, leaving out one factor at a time so t on a node is no special case.
+        let mut derivative = 0.0;
+        for k in (0..n).filter(|&k| k != i) {
+            let mut term = 1.0 / (nodes[i] - nodes[k]);
+            for j in (0..n).filter(|&j| j != i && j != k) {
+                term *= (t - nodes[j]) / (nodes[i] - nodes[j]);
+            }
+            derivative += term;
+        }
+        derivatives[i] = derivative;
+    }
+    (values, derivatives)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sat::tle::parse_tles;
+
+    /// UK-DMC 2 sampled with sgp4 every 5 minutes for 3 hours, and the sgp4 state at any time.
+    fn sampled() -> (Vec<StateSample>, impl Fn(&NaiveDateTime) -> Prediction) {
+        let elements = parse_tles(include_str!("../../common/tle2.txt")).elements.remove(0);
+        let constants = sgp4::Constants::from_elements(&elements).unwrap();
+        let epoch = elements.datetime;
+        let truth = move |time: &NaiveDateTime| {
+            let minutes = (*time - epoch).num_milliseconds() as f64 / 60_000.0;
+            constants.propagate(sgp4::MinutesSinceEpoch(minutes)).unwrap()
+        };
+        let samples = (0..=36)
+            .map(|index| {
+                let time = epoch + TimeDelta::minutes(5 * index);
+                let state = truth(&time);
+                StateSample { time, position: state.position, velocity: Some(state.velocity) }
+            })
+            .collect();
+        (samples, truth)
+    }
+
+    #[test]
+    fn interpolation_follows_the_orbit() {
+        let (samples, truth) = sampled();
+        let start = samples[0].time;
+        for (interpolation, points, tolerance) in [(Interpolation::Lagrange, 8, 0.01), (Interpolation::Hermite, 4, 0.01)] {
+            let ephemeris = Ephemeris::new("UK-DMC 2", samples.clone(), interpolation, points).unwrap();
+            for seconds in [0, 37, 150, 4321, 10_799, 10_800] {
+                let time = start + TimeDelta::seconds(seconds);
+                let (state, expected) = (ephemeris.state_at(&time).unwrap(), truth(&time));
+                for axis in 0..3 {
+                    assert!((state.position[axis] - expected.position[axis]).abs() < tolerance,
+                            "{:?} at {} s: {:?} vs {:?}", interpolation, seconds, state.position, expected.position);
+                    assert!((state.velocity[axis] - expected.velocity[axis]).abs() < tolerance / 10.0,
+                            "{:?} at {} s: {:?} vs {:?}", interpolation, seconds, state.velocity, expected.velocity);
+                }
+            }
+            assert!(ephemeris.state_at(&(start - TimeDelta::minutes(1))).is_err());
+            assert!(ephemeris.state_at(&(ephemeris.end() + TimeDelta::minutes(1))).is_err());
+        }
+        let period = Ephemeris::new("UK-DMC 2", samples, Interpolation::Lagrange, 8).unwrap().orbital_period().unwrap();
+        assert!((period.num_seconds() - 5878).abs() < 30, "{}", period);
+
+        let positions_only = vec![StateSample { velocity: None, ..sampled().0[0] }, sampled().0[1]];
+        assert!(Ephemeris::new("UK-DMC 2", positions_only, Interpolation::Hermite, 4).is_err());
+    }
+
+    #[test]
+    fn time_systems_to_utc() {
+        let time = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        assert_eq!(TimeSystem::Gps.to_utc(&time), time - TimeDelta::seconds(18));
+        assert_eq!(TimeSystem::Tai.to_utc(&time), time - TimeDelta::seconds(37));
+        assert_eq!(TimeSystem::BeiDou.to_utc(&time), time - TimeDelta::seconds(4));
+        assert_eq!(TimeSystem::Tt.to_utc(&time), time - TimeDelta::milliseconds(69_184));
+        // Just after the 2016 leap second TAI - UTC is already 37.
+        let leap = NaiveDate::from_ymd_opt(2017, 1, 1).unwrap().and_hms_opt(0, 0, 37).unwrap();
+        assert_eq!(TimeSystem::Tai.to_utc(&leap), leap - TimeDelta::seconds(37));
+    }
+}
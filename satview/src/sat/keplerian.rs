@@ -0,0 +1,211 @@
+//! purpose:
+//!     keplerian.rs builds sgp4 elements for satellites that have no TLE yet (planned ones) from
+//!     classical Keplerian elements, so they can be propagated like any other satellite and written
+//!     out as 3LE with tle::write_tles.
+//!     The semi-major axis is taken as the mean (Brouwer) one sgp4 propagates with. It is written as
+//!     the TLE (Kozai) mean motion that sgp4 turns back into it, with the same WGS-72 constants.
+//!     This is a mean element, not the average radius: J2 moves a near circular orbit's average radius
+//!     off it by a few km in LEO (about 9 km lower for an equatorial orbit at 700 km, 4 km higher for
+//!     a polar one). to_state reads the same elements as an osculating two-body orbit.
+
+use chrono::NaiveDateTime;
+use crate::sat::propagator::MU;
+use crate::sat::satellite::Satellite;
+
+/// Classical orbital elements of a planned satellite at an epoch (UTC).
+/// semi_major_axis in km, angles in degrees.
+/// norad_id must fit the 5 TLE columns, synthetic satellites conventionally use 9xxxx.
+/// international_designator as YYYY-NNNP, e.g. 2022-999A.
+/// drag_term is the SGP4 B* in 1/earth radii, 0 for no drag.
+#[derive(Debug, Clone)]
+pub struct KeplerianElements {
+    pub name: String,
+    pub norad_id: u64,
+    pub international_designator: Option<String>,
+    pub epoch: NaiveDateTime,
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub right_ascension: f64,
+    pub argument_of_perigee: f64,
+    pub mean_anomaly: f64,
+    pub drag_term: f64,
+}
+
+impl KeplerianElements {
+    /// to_elements: sgp4 elements for these Keplerian elements.
+    /// Angles other than the inclination are wrapped to 0..360. The TLE bookkeeping fields are
+    /// those of a fresh element set: unclassified, element set 999, revolution 0, no mean motion derivatives.
+    pub fn to_elements(&self) -> anyhow::Result<sgp4::Elements> {
+        self.check()?;
+        if self.norad_id > 99_999 {
+            anyhow::bail!("{} NORAD id {} does not fit the 5 TLE columns", self.name, self.norad_id);
+        }
+
+        Ok(sgp4::Elements {
+            object_name: Some(self.name.clone()),
+            international_designator: self.international_designator.clone(),
+            norad_id: self.norad_id,
+            classification: sgp4::Classification::Unclassified,
+            datetime: self.epoch,
+            mean_motion_dot: 0.0,
+            mean_motion_ddot: 0.0,
+            drag_term: self.drag_term,
+            element_set_number: 999,
+            inclination: self.inclination,
+            right_ascension: self.right_ascension.rem_euclid(360.0),
+            eccentricity: self.eccentricity,
+            argument_of_perigee: self.argument_of_perigee.rem_euclid(360.0),
+            mean_anomaly: self.mean_anomaly.rem_euclid(360.0),
+            mean_motion: mean_motion(self.semi_major_axis, self.eccentricity, self.inclination),
+            revolution_number: 0,
+            ephemeris_type: 0,
+        })
+    }
+
+    /// to_satellite: a satellite propagating these elements with sgp4.
+    pub fn to_satellite(&self) -> anyhow::Result<Satellite> {
+        Ok(Satellite::new(self.to_elements()?))
+    }
+
+    /// to_state: TEME position (km) and velocity (km/s) at the epoch of the two-body orbit with
+    /// these elements, for the two-body and numerical propagators (gravitational parameter MU).
+    pub fn to_state(&self) -> anyhow::Result<([f64; 3], [f64; 3])> {
+        self.check()?;
+        let e = self.eccentricity;
+        let mean_anomaly = self.mean_anomaly.to_radians().rem_euclid(2.0 * std::f64::consts::PI);
+        let mut eccentric_anomaly = if e < 0.8 { mean_anomaly } else { std::f64::consts::PI };
+        for _ in 0..50 {
+            let step = (eccentric_anomaly - e * eccentric_anomaly.sin() - mean_anomaly) / (1.0 - e * eccentric_anomaly.cos());
+            eccentric_anomaly -= step;
+            if step.abs() < 1e-14 {
+                break;
+            }
+        }
+        let true_anomaly = 2.0 * ((1.0 + e).sqrt() * (eccentric_anomaly / 2.0).sin())
+            .atan2((1.0 - e).sqrt() * (eccentric_anomaly / 2.0).cos());
+        let radius = self.semi_major_axis * (1.0 - e * eccentric_anomaly.cos());
+        let speed = (MU / (self.semi_major_axis * (1.0 - e * e))).sqrt();
+        let (sin_nu, cos_nu) = true_anomaly.sin_cos();
+        let perifocal_position = [radius * cos_nu, radius * sin_nu];
+        let perifocal_velocity = [-speed * sin_nu, speed * (e + cos_nu)];
+
+        // Perifocal to TEME: rotate by the argument of perigee, the inclination and the RAAN.
+        let (sin_w, cos_w) = self.argument_of_perigee.to_radians().sin_cos();
+        let (sin_i, cos_i) = self.inclination.to_radians().sin_cos();
+        let (sin_o, cos_o) = self.right_ascension.to_radians().sin_cos();
+        let p = [cos_o * cos_w - sin_o * sin_w * cos_i, sin_o * cos_w + cos_o * sin_w * cos_i, sin_w * sin_i];
+        let q = [-cos_o * sin_w - sin_o * cos_w * cos_i, -sin_o * sin_w + cos_o * cos_w * cos_i, cos_w * sin_i];
+        let rotate = |v: [f64; 2]| [p[0] * v[0] + q[0] * v[1], p[1] * v[0] + q[1] * v[1], p[2] * v[0] + q[2] * v[1]];
+        Ok((rotate(perifocal_position), rotate(perifocal_velocity)))
+    }
+
+    /// check: eccentricity, perigee and inclination describe an orbit above the earth.
+    fn check(&self) -> anyhow::Result<()> {
+        if !(0.0..1.0).contains(&self.eccentricity) {
+            anyhow::bail!("{} eccentricity {} is outside 0 to 1", self.name, self.eccentricity);
+        }
+        let perigee = self.semi_major_axis * (1.0 - self.eccentricity);
+        if perigee.is_nan() || perigee <= sgp4::WGS72.ae {
+            anyhow::bail!("{} perigee radius {:.1} km is inside the earth", self.name, perigee);
+        }
+        if !(0.0..=180.0).contains(&self.inclination) {
+            anyhow::bail!("{} inclination {} is outside 0 to 180 degrees", self.name, self.inclination);
+        }
+        Ok(())
+    }
+}
+
+/// mean_motion: TLE (Kozai) mean motion in rev/day of an orbit whose sgp4 (Brouwer) mean semi-major axis
+/// is semi_major_axis (km), WGS-72. Eccentricity and inclination (degrees) enter through J2.
+pub fn mean_motion(semi_major_axis: f64, eccentricity: f64, inclination: f64) -> f64 {
+    let brouwer = sgp4::WGS72.ke / (semi_major_axis / sgp4::WGS72.ae).powf(1.5);
+    // The two differ by well under a percent, so fixed point steps converge in a few rounds.
+    let mut kozai = brouwer;
+    for _ in 0..20 {
+        let step = brouwer / brouwer_mean_motion(kozai, eccentricity, inclination);
+        kozai *= step;
+        if (step - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    kozai * 1440.0 / (2.0 * std::f64::consts::PI)
+}
+
+/// semi_major_axis: sgp4 (Brouwer) mean semi-major axis in km of a TLE mean motion (rev/day), WGS-72.
+pub fn semi_major_axis(mean_motion: f64, eccentricity: f64, inclination: f64) -> f64 {
+    let kozai = mean_motion * 2.0 * std::f64::consts::PI / 1440.0;
+    sgp4::WGS72.ae * (sgp4::WGS72.ke / brouwer_mean_motion(kozai, eccentricity, inclination)).powf(2.0 / 3.0)
+}
+
+/// brouwer_mean_motion: the mean motion (rad/min) sgp4 recovers from a Kozai one, NaN when it is not positive.
+fn brouwer_mean_motion(kozai: f64, eccentricity: f64, inclination: f64) -> f64 {
+    sgp4::Orbit::from_kozai_elements(&sgp4::WGS72, inclination.to_radians(), 0.0, eccentricity, 0.0, 0.0, kozai)
+        .map_or(f64::NAN, |orbit| orbit.mean_motion)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::sat::tle::{parse_tles, write_tles};
+
+    fn planned() -> KeplerianElements {
+        KeplerianElements {
+            name: "MP07".to_string(),
+            norad_id: 99907,
+            international_designator: Some("2022-999G".to_string()),
+            epoch: NaiveDate::from_ymd_opt(2023, 8, 23).unwrap().and_hms_opt(13, 2, 0).unwrap(),
+            semi_major_axis: semi_major_axis(5.0011, 0.0004, 0.1),
+            eccentricity: 0.0004,
+            inclination: 0.1,
+            right_ascension: 349.5,
+            argument_of_perigee: -200.0,
+            mean_anomaly: 400.0,
+            drag_term: 0.0,
+        }
+    }
+
+    #[test]
+    fn synthesised_3le_loads_back() {
+        let elements = planned().to_elements().unwrap();
+        assert!((elements.argument_of_perigee - 160.0).abs() < 1e-9);
+        assert!((elements.mean_anomaly - 40.0).abs() < 1e-9);
+
+        let mut text = Vec::new();
+        write_tles(&mut text, &[planned().to_elements().unwrap()]).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert_eq!(text, "MP07
+1 99907U 22999G   23235.54305556  .00000000  00000-0  00000-0 0  9993
+2 99907   0.1000 349.5000 0004000 160.0000  40.0000  5.00110000    00
+");
+
+        let set = parse_tles(&text);
+        assert!(set.errors.is_empty(), "{:?}", set.errors);
+        let loaded = set.elements.into_iter().next().unwrap();
+        assert_eq!(loaded.international_designator.as_deref(), Some("2022-999G"));
+        assert!((loaded.datetime - elements.datetime).num_milliseconds().abs() < 1);
+        assert!((semi_major_axis(loaded.mean_motion, loaded.eccentricity, loaded.inclination) - planned().semi_major_axis).abs() < 1e-3);
+
+        // Propagating the loaded TLE lands where the built elements do.
+        let mut built = Satellite::new(elements);
+        let mut reloaded = Satellite::new(loaded);
+        let later = planned().epoch + chrono::TimeDelta::hours(6);
+        let (a, b) = (built.propagate(&later).unwrap().prediction, reloaded.propagate(&later).unwrap().prediction);
+        for i in 0..3 {
+            assert!((a.position[i] - b.position[i]).abs() < 0.1, "{:?} vs {:?}", a.position, b.position);
+        }
+    }
+
+    #[test]
+    fn mean_motion_is_kozai() {
+        for (axis, eccentricity, inclination) in [(7078.135, 0.001, 98.0), (7078.135, 0.0, 0.0),
+                                                  (14_446.256, 0.0004, 0.1), (26_560.0, 0.7, 63.4)] {
+            let kozai = mean_motion(axis, eccentricity, inclination);
+            assert!((semi_major_axis(kozai, eccentricity, inclination) - axis).abs() < 1e-6);
+            // J2 makes the Kozai mean motion the larger one below 54.7 degrees (3 cos^2 i > 1), the smaller above.
+            let two_body = sgp4::WGS72.ke / (axis / sgp4::WGS72.ae).powf(1.5) * 1440.0 / (2.0 * std::f64::consts::PI);
+            assert_eq!(kozai > two_body, inclination < 54.7, "{} rev/day at {} degrees", kozai, inclination);
+        }
+    }
+}
@@ -1 +1,13 @@
-pub mod satellite;
\ No newline at end of file
+pub mod satellite;
+pub mod tle;
+pub mod staleness;
+pub mod omm;
+pub mod keplerian;
+pub mod constellation;
+pub mod ephemeris;
+pub mod oem;
+pub mod sp3;
+pub mod orbit_file;
+pub mod propagator;
+pub mod twobody;
+pub mod numerical;
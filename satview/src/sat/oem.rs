@@ -0,0 +1,254 @@
+//! purpose:
+//!     oem.rs loads CCSDS Orbit Ephemeris Messages (OEM), the precise ephemerides operators hand out
+//!     instead of TLEs, in KVN (META_START / META_STOP blocks followed by state lines) or XML.
+//!     Each segment brings its own object, frame, time system and interpolation hint; the states are
+//!     converted to UTC and TEME (ephemeris.rs) and the segments of one object are joined into one Ephemeris.
+//!     Covariance and acceleration data are ignored. A file that cannot be read fails as a whole,
+//!     with the line of the problem.
+
+use std::collections::HashMap;
+use crate::sat::ephemeris::{Ephemeris, Interpolation, ReferenceFrame, StateSample, TimeSystem,
+                            DEFAULT_HERMITE_POINTS, DEFAULT_LAGRANGE_POINTS};
+use crate::sat::omm::parse_ccsds_epoch;
+
+/// One segment read from an OEM: its metadata keywords and the states as given (time scale and frame of the file).
+struct Segment {
+    line: usize,
+    metadata: HashMap<String, String>,
+    states: Vec<(chrono::NaiveDateTime, [f64; 3], [f64; 3])>,
+}
+
+/// parse_oem: every object of a KVN or XML OEM, one Ephemeris each, in order of first appearance.
+/// Interpolation follows the INTERPOLATION and INTERPOLATION_DEGREE of the first segment of an object,
+/// Lagrange with DEFAULT_LAGRANGE_POINTS when it has none.
+pub fn parse_oem(text: &str) -> anyhow::Result<Vec<Ephemeris>> {
+    let text = text.trim_start_matches('\u{feff}');
+    let segments = if text.trim_start().starts_with('<') {
+        parse_oem_xml(text)?
+    } else {
+        parse_oem_kvn(text)?
+    };
+    if segments.is_empty() {
+        anyhow::bail!("no segment in the OEM");
+    }
+
+    let mut objects: Vec<(String, Vec<StateSample>, Interpolation, usize)> = Vec::new();
+    for segment in segments {
+        let (name, samples) = segment_samples(&segment)
+            .map_err(|err| anyhow::anyhow!("segment at line {}: {}", segment.line, err))?;
+        match objects.iter_mut().find(|object| object.0 == name) {
+            Some(object) => object.1.extend(samples),
+            None => {
+                let (interpolation, points) = segment_interpolation(&segment)
+                    .map_err(|err| anyhow::anyhow!("segment at line {}: {}", segment.line, err))?;
+                objects.push((name, samples, interpolation, points));
+            }
+        }
+    }
+    objects.into_iter()
+        .map(|(name, samples, interpolation, points)| Ephemeris::new(&name, samples, interpolation, points))
+        .collect()
+}
+
+/// parse_oem_kvn: segments of a KVN OEM. Header keywords before the first META_START are skipped.
+fn parse_oem_kvn(text: &str) -> anyhow::Result<Vec<Segment>> {
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut in_metadata = false;
+    let mut in_covariance = false;
+    for (index, raw) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with("COMMENT") {
+            continue;
+        }
+        match line {
+            "META_START" => {
+                segments.push(Segment { line: number, metadata: HashMap::new(), states: Vec::new() });
+                in_metadata = true;
+                continue;
+            }
+            "META_STOP" => {
+                in_metadata = false;
+                continue;
+            }
+            "COVARIANCE_START" => {
+                in_covariance = true;
+                continue;
+            }
+            "COVARIANCE_STOP" => {
+                in_covariance = false;
+                continue;
+            }
+            _ => {}
+        }
+        if in_covariance {
+            continue;
+        }
+        let Some(segment) = segments.last_mut() else {
+            // Header: CCSDS_OEM_VERS, CREATION_DATE, ORIGINATOR, ...
+            continue;
+        };
+        if in_metadata {
+            let (keyword, value) = line.split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("line {}: {:?} is not KEYWORD = value", number, line))?;
+            segment.metadata.insert(keyword.trim().to_string(), value.trim().to_string());
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 7 && fields.len() != 10 {
+            anyhow::bail!("line {}: a state line needs an epoch and 6 (or 9) numbers, got {:?}", number, line);
+        }
+        let time = parse_ccsds_epoch(fields[0]).map_err(|err| anyhow::anyhow!("line {}: {}", number, err))?;
+        let mut numbers = [0.0; 6];
+        for (value, field) in numbers.iter_mut().zip(&fields[1..7]) {
+            *value = field.parse()
+                .map_err(|_| anyhow::anyhow!("line {}: {:?} is not a number", number, field))?;
+        }
+        segment.states.push((time, [numbers[0], numbers[1], numbers[2]], [numbers[3], numbers[4], numbers[5]]));
+    }
+    Ok(segments)
+}
+
+/// parse_oem_xml: segments of an XML OEM, <segment> elements holding <metadata> and <stateVector>s.
+fn parse_oem_xml(text: &str) -> anyhow::Result<Vec<Segment>> {
+    let document = roxmltree::Document::parse(text)
+        .map_err(|err| anyhow::anyhow!("invalid OEM XML: {}", err))?;
+    let mut segments = Vec::new();
+    for segment in document.descendants().filter(|node| node.is_element() && node.tag_name().name() == "segment") {
+        let line = document.text_pos_at(segment.range().start).row as usize;
+        let mut metadata = HashMap::new();
+        if let Some(meta) = child_element(&segment, "metadata") {
+            for node in meta.children().filter(|node| node.is_element()) {
+                metadata.insert(node.tag_name().name().to_string(), node.text().unwrap_or("").trim().to_string());
+            }
+        }
+        let mut states = Vec::new();
+        for state in segment.descendants().filter(|node| node.is_element() && node.tag_name().name() == "stateVector") {
+            let state_line = document.text_pos_at(state.range().start).row;
+            let value = |name: &str| child_element(&state, name)
+                .and_then(|node| node.text())
+                .map(str::trim)
+                .ok_or_else(|| anyhow::anyhow!("line {}: stateVector has no {}", state_line, name));
+            let number = |name: &str| value(name)?.parse::<f64>()
+                .map_err(|_| anyhow::anyhow!("line {}: {} is not a number", state_line, name));
+            let time = parse_ccsds_epoch(value("EPOCH")?).map_err(|err| anyhow::anyhow!("line {}: {}", state_line, err))?;
+            states.push((time,
+                         [number("X")?, number("Y")?, number("Z")?],
+                         [number("X_DOT")?, number("Y_DOT")?, number("Z_DOT")?]));
+        }
+        segments.push(Segment { line, metadata, states });
+    }
+    Ok(segments)
+}
+
+/// child_element: the first child element of a node with this name.
+fn child_element<'a, 'input>(node: &roxmltree::Node<'a, 'input>, name: &str) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|child| child.is_element() && child.tag_name().name() == name)
+}
+
+/// segment_samples: object name and UTC/TEME samples of a segment.
+fn segment_samples(segment: &Segment) -> anyhow::Result<(String, Vec<StateSample>)> {
+    let keyword = |name: &str| segment.metadata.get(name).map(String::as_str);
+    let name = keyword("OBJECT_NAME")
+        .or(keyword("OBJECT_ID"))
+        .ok_or_else(|| anyhow::anyhow!("no OBJECT_NAME"))?;
+    let center = keyword("CENTER_NAME").unwrap_or("EARTH");
+    if !center.eq_ignore_ascii_case("EARTH") {
+        anyhow::bail!("{} is centred on {}, only EARTH is supported", name, center);
+    }
+    let frame = ReferenceFrame::parse(keyword("REF_FRAME").ok_or_else(|| anyhow::anyhow!("no REF_FRAME"))?)?;
+    let time_system = TimeSystem::parse(keyword("TIME_SYSTEM").unwrap_or("UTC"))?;
+
+    let samples = segment.states.iter()
+        .map(|(time, position, velocity)| {
+            let time = time_system.to_utc(time);
+            let (position, velocity) = frame.to_teme(&time, *position, Some(*velocity));
+            StateSample { time, position, velocity }
+        })
+        .collect();
+    Ok((name.to_string(), samples))
+}
+
+/// segment_interpolation: method and points from INTERPOLATION and INTERPOLATION_DEGREE.
+/// Methods other than Lagrange and Hermite (LINEAR, ...) fall back to Lagrange.
+fn segment_interpolation(segment: &Segment) -> anyhow::Result<(Interpolation, usize)> {
+    let interpolation = match segment.metadata.get("INTERPOLATION").map(|method| method.to_ascii_uppercase()) {
+        Some(method) if method == "HERMITE" => Interpolation::Hermite,
+        _ => Interpolation::Lagrange,
+    };
+    let points = match segment.metadata.get("INTERPOLATION_DEGREE") {
+        Some(degree) => {
+            let degree: usize = degree.parse()
+                .map_err(|_| anyhow::anyhow!("INTERPOLATION_DEGREE {:?} is not a whole number", degree))?;
+            interpolation.points_for_degree(degree).max(2)
+        }
+        None => match interpolation {
+            Interpolation::Lagrange => DEFAULT_LAGRANGE_POINTS,
+            Interpolation::Hermite => DEFAULT_HERMITE_POINTS,
+        },
+    };
+    Ok((interpolation, points))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+    use crate::sat::satellite::Satellite;
+    use crate::sat::tle::parse_tles;
+
+    /// UK-DMC 2 every 2 minutes for an hour from its TLE epoch, UTC and TEME.
+    fn states() -> Vec<(chrono::NaiveDateTime, [f64; 3], [f64; 3])> {
+        let mut satellite = Satellite::new(parse_tles(include_str!("../../common/tle2.txt")).elements.remove(0));
+        let epoch = satellite.reference_epoch();
+        (0..=30)
+            .map(|index| {
+                let time = epoch + TimeDelta::minutes(2 * index);
+                let state = satellite.propagate(&time).unwrap().prediction;
+                (time, state.position, state.velocity)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn kvn_and_xml_segments_join_into_one_ephemeris() {
+        let states = states();
+        // GPS time is UTC + 16 s in 2012.
+        let gps = |time: &chrono::NaiveDateTime| (*time + TimeDelta::seconds(16)).format("%Y-%m-%dT%H:%M:%S%.6f");
+        let metadata = "OBJECT_NAME = UK-DMC 2\nOBJECT_ID = 2009-041C\nCENTER_NAME = EARTH\nREF_FRAME = TEME\n\
+                        TIME_SYSTEM = GPS\nINTERPOLATION = HERMITE\nINTERPOLATION_DEGREE = 5\n";
+        let mut kvn = String::from("CCSDS_OEM_VERS = 2.0\nCREATION_DATE = 2012-10-15T06:00:00\nORIGINATOR = SSTL\n");
+        for (index, segment) in states.chunks(16).enumerate() {
+            kvn += &format!("\nMETA_START\n{}META_STOP\nCOMMENT segment {}\n", metadata, index + 1);
+            for (time, r, v) in segment {
+                kvn += &format!("{} {:.6} {:.6} {:.6} {:.9} {:.9} {:.9}\n", gps(time), r[0], r[1], r[2], v[0], v[1], v[2]);
+            }
+            kvn += "COVARIANCE_START\nEPOCH = 2012-10-15T06:00:00\n1.0e-3\nCOVARIANCE_STOP\n";
+        }
+        let mut xml = String::from("<?xml version=\"1.0\"?>\n<oem id=\"CCSDS_OEM_VERS\" version=\"2.0\"><body><segment><metadata>\n\
+                                    <OBJECT_NAME>UK-DMC 2</OBJECT_NAME><CENTER_NAME>EARTH</CENTER_NAME><REF_FRAME>TEME</REF_FRAME>\n\
+                                    <TIME_SYSTEM>GPS</TIME_SYSTEM></metadata><data>\n");
+        for (time, r, v) in &states {
+            xml += &format!("<stateVector><EPOCH>{}</EPOCH><X>{}</X><Y>{}</Y><Z>{}</Z><X_DOT>{}</X_DOT><Y_DOT>{}</Y_DOT><Z_DOT>{}</Z_DOT></stateVector>\n",
+                            gps(time), r[0], r[1], r[2], v[0], v[1], v[2]);
+        }
+        xml += "</data></segment></body></oem>\n";
+
+        for (text, interpolation) in [(kvn, (Interpolation::Hermite, 3)), (xml, (Interpolation::Lagrange, DEFAULT_LAGRANGE_POINTS))] {
+            let ephemerides = parse_oem(&text).unwrap();
+            assert_eq!(ephemerides.len(), 1);
+            let ephemeris = &ephemerides[0];
+            assert_eq!(ephemeris.name, "UK-DMC 2");
+            assert_eq!(ephemeris.interpolation(), interpolation);
+            assert_eq!(ephemeris.samples().len(), states.len());
+            assert!((ephemeris.start() - states[0].0).abs() < TimeDelta::milliseconds(1));
+            let (time, position, _) = states[7];
+            let state = ephemeris.state_at(&time).unwrap();
+            for axis in 0..3 {
+                assert!((state.position[axis] - position[axis]).abs() < 1e-5, "{:?} vs {:?}", state.position, position);
+            }
+        }
+        assert!(parse_oem("CCSDS_OEM_VERS = 2.0\nMETA_START\nOBJECT_NAME = X\nCENTER_NAME = MOON\nREF_FRAME = ICRF\nMETA_STOP\n").is_err());
+    }
+}
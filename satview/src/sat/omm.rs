@@ -0,0 +1,501 @@
+//! purpose:
+//!     omm.rs loads CCSDS Orbit Mean-Elements Messages (OMM), the GP data formats CelesTrak and
+//!     Space-Track publish next to 3LE: JSON, XML, CSV and KVN (KEYWORD = value lines).
+//!     Every format is reduced to OMM keyword/value pairs (OBJECT_NAME, EPOCH, MEAN_MOTION, ...)
+//!     which sgp4 turns into the same Elements a 3LE gives.
+//!     load_orbits detects the format from the content, so any of them (or 2LE/3LE) can be given
+//!     wherever a TLE file is expected. Like parse_tles, a bad record is reported and skipped.
+//!     detect_format also recognises the ephemeris formats (OEM, SP3), which orbit_file.rs loads.
+
+use std::path::Path;
+use serde_json::{Map, Value};
+use crate::sat::tle::{parse_tles, TleError, TleSet};
+
+/// Mean element theories whose elements sgp4 can propagate.
+const SGP4_THEORIES: [&str; 2] = ["SGP4", "SGP/SGP4"];
+
+/// Values for the TLE parameters an OMM may leave out, the same a TLE writer would use.
+const OPTIONAL_DEFAULTS: [(&str, &str); 5] = [
+    ("CLASSIFICATION_TYPE", "U"),
+    ("EPHEMERIS_TYPE", "0"),
+    ("ELEMENT_SET_NO", "999"),
+    ("REV_AT_EPOCH", "0"),
+    ("MEAN_MOTION_DDOT", "0"),
+];
+
+/// Orbit data formats load_orbits understands.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OrbitFormat {
+    Tle,
+    OmmJson,
+    OmmXml,
+    OmmCsv,
+    OmmKvn,
+    Oem,
+    Sp3,
+}
+
+/// One OMM read from a file: the line it starts on, its keyword/value pairs and the first
+/// problem found while reading it, with the line of that problem.
+struct OmmRecord {
+    line: usize,
+    fields: Map<String, Value>,
+    problem: Option<(usize, String)>,
+}
+
+/// detect_format: format of an orbit file from its first meaningful line.
+/// SP3 starts with #a to #d and P or V, an OEM with CCSDS_OEM_VERS or, in XML, has an <oem> element.
+/// JSON starts with '[' or '{', XML with '<', KVN with KEYWORD = value, CSV with a header naming EPOCH.
+/// Anything else is taken to be 2LE/3LE.
+pub fn detect_format(text: &str) -> OrbitFormat {
+    let header = text.trim_start_matches('\u{feff}').as_bytes();
+    if header.len() > 2 && header[0] == b'#' && (b'a'..=b'd').contains(&header[1]) && matches!(header[2], b'P' | b'V') {
+        return OrbitFormat::Sp3;
+    }
+    let first = text.lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .unwrap_or("");
+    if first.starts_with('[') || first.starts_with('{') {
+        OrbitFormat::OmmJson
+    } else if first.starts_with("CCSDS_OEM_VERS") || (first.starts_with('<') && text.contains("<oem")) {
+        OrbitFormat::Oem
+    } else if first.starts_with('<') {
+        OrbitFormat::OmmXml
+    } else if first.starts_with("COMMENT") || is_kvn_line(first) {
+        OrbitFormat::OmmKvn
+    } else if first.contains(',') && first.split(',').any(|column| column.trim().trim_matches('"') == "EPOCH") {
+        OrbitFormat::OmmCsv
+    } else {
+        OrbitFormat::Tle
+    }
+}
+
+/// parse_orbits: loads every record of a 2LE/3LE or OMM text, detecting the format.
+/// Fails when an OMM file as a whole cannot be read (bad JSON/XML syntax, bad CSV header)
+/// or the text is an ephemeris, bad records are returned in TleSet::errors.
+pub fn parse_orbits(text: &str) -> anyhow::Result<TleSet> {
+    match detect_format(text) {
+        OrbitFormat::Oem | OrbitFormat::Sp3 => anyhow::bail!("ephemeris files have no mean elements, load them with orbit_file::parse_orbit_file"),
+        OrbitFormat::Tle => Ok(parse_tles(text)),
+        OrbitFormat::OmmJson => parse_omm_json(text),
+        OrbitFormat::OmmXml => parse_omm_xml(text),
+        OrbitFormat::OmmCsv => parse_omm_csv(text),
+        OrbitFormat::OmmKvn => Ok(parse_omm_kvn(text)),
+    }
+}
+
+/// load_orbits: reads and parses a 2LE/3LE or OMM file, see parse_orbits.
+pub fn load_orbits(path: &Path) -> anyhow::Result<TleSet> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("could not read orbit file {}: {}", path.display(), err))?;
+    parse_orbits(&text).map_err(|err| anyhow::anyhow!("{}: {}", path.display(), err))
+}
+
+/// parse_omm_json: a JSON array of OMM objects, as served by CelesTrak and Space-Track, or a single object.
+/// Numbers may be given as JSON numbers or strings.
+pub fn parse_omm_json(text: &str) -> anyhow::Result<TleSet> {
+    let text = text.trim_start_matches('\u{feff}');
+    let value: Value = serde_json::from_str(text)
+        .map_err(|err| anyhow::anyhow!("invalid OMM JSON: {}", err))?;
+    let values = match value {
+        Value::Array(values) => values,
+        Value::Object(_) => vec![value],
+        _ => anyhow::bail!("OMM JSON must be an object or an array of objects"),
+    };
+
+    let lines = json_record_lines(text);
+    let records = values.into_iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let line = lines.get(index).copied().unwrap_or(1);
+            match value {
+                Value::Object(fields) => OmmRecord { line, fields, problem: None },
+                _ => OmmRecord { line, fields: Map::new(), problem: Some((line, "record is not a JSON object".to_string())) },
+            }
+        })
+        .collect();
+    Ok(collect_records(records))
+}
+
+/// parse_omm_xml: every <omm> element of an XML document (usually an <ndm> holding many).
+/// Keywords are the leaf element names, namespaces are ignored.
+pub fn parse_omm_xml(text: &str) -> anyhow::Result<TleSet> {
+    let document = roxmltree::Document::parse(text.trim_start_matches('\u{feff}'))
+        .map_err(|err| anyhow::anyhow!("invalid OMM XML: {}", err))?;
+    let records: Vec<OmmRecord> = document.descendants()
+        .filter(|node| node.is_element() && node.tag_name().name() == "omm")
+        .map(|omm| {
+            let mut fields = Map::new();
+            for node in omm.descendants().filter(|node| node.is_element() && !node.children().any(|child| child.is_element())) {
+                let keyword = node.tag_name().name();
+                let value = node.text().unwrap_or("").trim();
+                if keyword != "COMMENT" && !value.is_empty() {
+                    fields.entry(keyword).or_insert_with(|| Value::String(value.to_string()));
+                }
+            }
+            OmmRecord { line: document.text_pos_at(omm.range().start).row as usize, fields, problem: None }
+        })
+        .collect();
+    if records.is_empty() {
+        anyhow::bail!("no <omm> element in the XML");
+    }
+    Ok(collect_records(records))
+}
+
+/// parse_omm_csv: a CSV table with a header row of OMM keywords and one OMM per row.
+/// Empty cells count as missing, '#' lines are comments.
+pub fn parse_omm_csv(text: &str) -> anyhow::Result<TleSet> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .comment(Some(b'#'))
+        .from_reader(text.trim_start_matches('\u{feff}').as_bytes());
+    let header = reader.headers()
+        .map_err(|err| anyhow::anyhow!("invalid OMM CSV header: {}", err))?
+        .clone();
+
+    let records = reader.records()
+        .map(|row| match row {
+            Ok(row) => {
+                let fields = header.iter()
+                    .zip(row.iter())
+                    .filter(|(_, value)| !value.is_empty())
+                    .map(|(keyword, value)| (keyword.to_string(), Value::String(value.to_string())))
+                    .collect();
+                OmmRecord { line: row.position().map_or(0, |position| position.line() as usize), fields, problem: None }
+            }
+            Err(err) => {
+                let line = err.position().map_or(0, |position| position.line() as usize);
+                OmmRecord { line, fields: Map::new(), problem: Some((line, err.to_string())) }
+            }
+        })
+        .collect();
+    Ok(collect_records(records))
+}
+
+/// parse_omm_kvn: KVN messages, one KEYWORD = value per line, COMMENT lines ignored.
+/// A message starts at CCSDS_OMM_VERS or, when that is left out, at a keyword the current one already has.
+/// Units in square brackets after a value are dropped.
+pub fn parse_omm_kvn(text: &str) -> TleSet {
+    let mut records: Vec<OmmRecord> = Vec::new();
+    for (index, raw) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = raw.trim_start_matches('\u{feff}').trim();
+        if line.is_empty() || line.starts_with("COMMENT") {
+            continue;
+        }
+
+        let (keyword, value) = match line.split_once('=') {
+            Some((keyword, value)) => (keyword.trim(), strip_units(value.trim())),
+            None => {
+                match records.last_mut() {
+                    Some(record) => {
+                        record.problem.get_or_insert((number, format!("{:?} is not KEYWORD = value", line)));
+                    }
+                    None => records.push(OmmRecord {
+                        line: number,
+                        fields: Map::new(),
+                        problem: Some((number, format!("{:?} is not KEYWORD = value", line))),
+                    }),
+                }
+                continue;
+            }
+        };
+        let starts_message = keyword == "CCSDS_OMM_VERS"
+            || records.last().is_none_or(|record| record.fields.contains_key(keyword));
+        if starts_message {
+            records.push(OmmRecord { line: number, fields: Map::new(), problem: None });
+        }
+        if let Some(record) = records.last_mut() {
+            if !value.is_empty() {
+                record.fields.insert(keyword.to_string(), Value::String(value.to_string()));
+            }
+        }
+    }
+    collect_records(records)
+}
+
+/// collect_records: turns read records into elements, one error per record that does not convert.
+fn collect_records(records: Vec<OmmRecord>) -> TleSet {
+    let mut elements = Vec::new();
+    let mut errors = Vec::new();
+    for record in records {
+        let name = record.fields.get("OBJECT_NAME").and_then(Value::as_str).map(str::to_string);
+        let result = match record.problem {
+            Some(problem) => Err(problem),
+            None => elements_from_fields(record.fields).map_err(|message| (record.line, message)),
+        };
+        match result {
+            Ok(element) => elements.push(element),
+            Err((line, message)) => errors.push(TleError { line, name, message }),
+        }
+    }
+    TleSet { elements, errors }
+}
+
+/// elements_from_fields: sgp4 elements from OMM keyword/value pairs.
+/// Checks the mean element theory, fills in OPTIONAL_DEFAULTS and accepts day-of-year epochs.
+fn elements_from_fields(mut fields: Map<String, Value>) -> Result<sgp4::Elements, String> {
+    if let Some(theory) = fields.get("MEAN_ELEMENT_THEORY").and_then(Value::as_str) {
+        if !SGP4_THEORIES.contains(&theory.trim()) {
+            return Err(format!("mean element theory {} is not SGP4", theory));
+        }
+    }
+    for (keyword, value) in OPTIONAL_DEFAULTS {
+        fields.entry(keyword).or_insert_with(|| Value::String(value.to_string()));
+    }
+    if let Some(Value::String(epoch)) = fields.get_mut("EPOCH") {
+        *epoch = normalize_epoch(epoch)?;
+    }
+    serde_path_to_error::deserialize(Value::Object(fields))
+        .map_err(|err| match err.path().to_string().as_str() {
+            "." => err.inner().to_string(),
+            path => format!("{}: {}", path, err.inner()),
+        })
+}
+
+/// normalize_epoch: an OMM epoch (calendar or day-of-year, optional trailing Z) as YYYY-MM-DDTHH:MM:SS.ffffff.
+fn normalize_epoch(epoch: &str) -> Result<String, String> {
+    parse_ccsds_epoch(epoch)
+        .map(|time| time.format("%Y-%m-%dT%H:%M:%S%.6f").to_string())
+        .map_err(|_| format!("EPOCH {:?} is not a UTC time like 2024-04-10T13:00:00.000000", epoch.trim()))
+}
+
+/// parse_ccsds_epoch: a CCSDS time, calendar (2024-04-10T13:00:00.000) or day-of-year (2024-101T13:00:00),
+/// with an optional trailing Z. The time scale is up to the message.
+pub fn parse_ccsds_epoch(epoch: &str) -> Result<chrono::NaiveDateTime, String> {
+    let epoch = epoch.trim().trim_end_matches('Z');
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%jT%H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(epoch, format).ok())
+        .ok_or_else(|| format!("{:?} is not a CCSDS time like 2024-04-10T13:00:00.000", epoch))
+}
+
+/// strip_units: "15.5 [rev/day]" to "15.5".
+fn strip_units(value: &str) -> &str {
+    match (value.ends_with(']'), value.rfind('[')) {
+        (true, Some(start)) => value[..start].trim_end(),
+        _ => value,
+    }
+}
+
+fn is_kvn_line(line: &str) -> bool {
+    line.split_once('=').is_some_and(|(keyword, _)| {
+        let keyword = keyword.trim();
+        !keyword.is_empty() && keyword.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+    })
+}
+
+/// json_record_lines: 1-based line on which each record of a JSON OMM starts,
+/// the elements of a top-level array or the top-level object itself.
+fn json_record_lines(text: &str) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut line = 1;
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    // Whether the next value inside the top-level array starts a record.
+    let mut expecting = false;
+    for character in text.chars() {
+        if in_string {
+            match character {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match character {
+            '\n' => line += 1,
+            ',' if depth == 1 => expecting = true,
+            _ if character.is_whitespace() || character == ',' => {}
+            _ => {
+                if depth == 0 && character == '{' || depth == 1 && expecting && character != ']' {
+                    lines.push(line);
+                    expecting = false;
+                }
+                match character {
+                    '"' => in_string = true,
+                    '[' | '{' => {
+                        if depth == 0 && character == '[' {
+                            expecting = true;
+                        }
+                        depth += 1;
+                    }
+                    ']' | '}' => depth -= 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeDelta};
+
+    /// UK-DMC 2 from common/tle2.txt, and the same elements as OMM in every format.
+    const TLE: &str = "UK-DMC 2
+1 35683U 09041C   12289.23158813  .00000484  00000-0  89219-4 0  5863
+2 35683  98.0221 185.3682 0001499 100.5295 259.6088 14.69819587172294
+";
+
+    const JSON: &str = r#"[{
+    "OBJECT_NAME": "UK-DMC 2",
+    "OBJECT_ID": "2009-041C",
+    "EPOCH": "2012-10-15T05:33:29.214432",
+    "MEAN_MOTION": 14.69819587,
+    "ECCENTRICITY": 0.0001499,
+    "INCLINATION": 98.0221,
+    "RA_OF_ASC_NODE": 185.3682,
+    "ARG_OF_PERICENTER": 100.5295,
+    "MEAN_ANOMALY": 259.6088,
+    "EPHEMERIS_TYPE": 0,
+    "CLASSIFICATION_TYPE": "U",
+    "NORAD_CAT_ID": 35683,
+    "ELEMENT_SET_NO": 586,
+    "REV_AT_EPOCH": 17229,
+    "BSTAR": 8.9219e-5,
+    "MEAN_MOTION_DOT": 4.84e-6,
+    "MEAN_MOTION_DDOT": 0
+}]"#;
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ndm xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:noNamespaceSchemaLocation="https://sanaregistry.org/r/ndmxml_unqualified/ndmxml-2.0.0-master-2.0.xsd">
+<omm id="CCSDS_OMM_VERS" version="2.0">
+<header><CREATION_DATE/><ORIGINATOR/></header>
+<body><segment>
+<metadata><OBJECT_NAME>UK-DMC 2</OBJECT_NAME><OBJECT_ID>2009-041C</OBJECT_ID><CENTER_NAME>EARTH</CENTER_NAME><REF_FRAME>TEME</REF_FRAME><TIME_SYSTEM>UTC</TIME_SYSTEM><MEAN_ELEMENT_THEORY>SGP4</MEAN_ELEMENT_THEORY></metadata>
+<data><meanElements><EPOCH>2012-10-15T05:33:29.214432</EPOCH><MEAN_MOTION>14.69819587</MEAN_MOTION><ECCENTRICITY>.0001499</ECCENTRICITY><INCLINATION>98.0221</INCLINATION><RA_OF_ASC_NODE>185.3682</RA_OF_ASC_NODE><ARG_OF_PERICENTER>100.5295</ARG_OF_PERICENTER><MEAN_ANOMALY>259.6088</MEAN_ANOMALY></meanElements>
+<tleParameters><EPHEMERIS_TYPE>0</EPHEMERIS_TYPE><CLASSIFICATION_TYPE>U</CLASSIFICATION_TYPE><NORAD_CAT_ID>35683</NORAD_CAT_ID><ELEMENT_SET_NO>586</ELEMENT_SET_NO><REV_AT_EPOCH>17229</REV_AT_EPOCH><BSTAR>.89219E-4</BSTAR><MEAN_MOTION_DOT>.484E-5</MEAN_MOTION_DOT><MEAN_MOTION_DDOT>0</MEAN_MOTION_DDOT></tleParameters></data>
+</segment></body></omm>
+</ndm>
+"#;
+
+    const CSV: &str = "OBJECT_NAME,OBJECT_ID,EPOCH,MEAN_MOTION,ECCENTRICITY,INCLINATION,RA_OF_ASC_NODE,ARG_OF_PERICENTER,MEAN_ANOMALY,EPHEMERIS_TYPE,CLASSIFICATION_TYPE,NORAD_CAT_ID,ELEMENT_SET_NO,REV_AT_EPOCH,BSTAR,MEAN_MOTION_DOT,MEAN_MOTION_DDOT
+UK-DMC 2,2009-041C,2012-10-15T05:33:29.214432,14.69819587,.0001499,98.0221,185.3682,100.5295,259.6088,0,U,35683,586,17229,.89219E-4,.484E-5,0
+";
+
+    const KVN: &str = "CCSDS_OMM_VERS = 2.0
+COMMENT from common/tle2.txt
+OBJECT_NAME = UK-DMC 2
+OBJECT_ID = 2009-041C
+CENTER_NAME = EARTH
+REF_FRAME = TEME
+TIME_SYSTEM = UTC
+MEAN_ELEMENT_THEORY = SGP4
+EPOCH = 2012-289T05:33:29.214432
+MEAN_MOTION = 14.69819587 [rev/day]
+ECCENTRICITY = .0001499
+INCLINATION = 98.0221 [deg]
+RA_OF_ASC_NODE = 185.3682 [deg]
+ARG_OF_PERICENTER = 100.5295 [deg]
+MEAN_ANOMALY = 259.6088 [deg]
+EPHEMERIS_TYPE = 0
+CLASSIFICATION_TYPE = U
+NORAD_CAT_ID = 35683
+ELEMENT_SET_NO = 586
+REV_AT_EPOCH = 17229
+BSTAR = .89219E-4 [1/ER]
+MEAN_MOTION_DOT = .484E-5 [rev/day**2]
+MEAN_MOTION_DDOT = 0 [rev/day**3]
+";
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= tolerance,
+                "expected {} got {} (tolerance {})", expected, actual, tolerance);
+    }
+
+    fn only_element(text: &str) -> sgp4::Elements {
+        let set = parse_orbits(text).unwrap();
+        assert!(set.errors.is_empty(), "{:?}", set.errors);
+        assert_eq!(set.elements.len(), 1);
+        set.elements.into_iter().next().unwrap()
+    }
+
+    fn assert_same_elements(omm: &sgp4::Elements, tle: &sgp4::Elements) {
+        assert_eq!(omm.object_name, tle.object_name);
+        assert_eq!(omm.norad_id, tle.norad_id);
+        assert_eq!(omm.international_designator.as_deref(), Some("2009-041C"));
+        assert_eq!(omm.element_set_number, tle.element_set_number);
+        assert_eq!(omm.revolution_number, tle.revolution_number);
+        assert!((omm.datetime - tle.datetime).abs() < TimeDelta::milliseconds(1),
+                "epoch {} vs {}", omm.datetime, tle.datetime);
+        assert_close(omm.mean_motion, tle.mean_motion, 1e-12);
+        assert_close(omm.eccentricity, tle.eccentricity, 1e-12);
+        assert_close(omm.inclination, tle.inclination, 1e-12);
+        assert_close(omm.right_ascension, tle.right_ascension, 1e-12);
+        assert_close(omm.argument_of_perigee, tle.argument_of_perigee, 1e-12);
+        assert_close(omm.mean_anomaly, tle.mean_anomaly, 1e-12);
+        assert_close(omm.drag_term, tle.drag_term, 1e-15);
+        assert_close(omm.mean_motion_dot, tle.mean_motion_dot, 1e-15);
+
+        // A day out, both propagate to the same place.
+        let time = NaiveDate::from_ymd_opt(2012, 10, 16).unwrap().and_hms_opt(12, 0, 0).unwrap();
+        let at = |elements: &sgp4::Elements| {
+            let constants = sgp4::Constants::from_elements(elements).unwrap();
+            constants.propagate(elements.datetime_to_minutes_since_epoch(&time).unwrap()).unwrap()
+        };
+        let (omm, tle) = (at(omm), at(tle));
+        for i in 0..3 {
+            assert_close(omm.position[i], tle.position[i], 1e-3);
+            assert_close(omm.velocity[i], tle.velocity[i], 1e-6);
+        }
+    }
+
+    #[test]
+    fn detects_formats() {
+        assert_eq!(detect_format(TLE), OrbitFormat::Tle);
+        assert_eq!(detect_format(&TLE.lines().skip(1).collect::<Vec<_>>().join("\n")), OrbitFormat::Tle);
+        assert_eq!(detect_format(JSON), OrbitFormat::OmmJson);
+        assert_eq!(detect_format(XML), OrbitFormat::OmmXml);
+        assert_eq!(detect_format(CSV), OrbitFormat::OmmCsv);
+        assert_eq!(detect_format(KVN), OrbitFormat::OmmKvn);
+        assert_eq!(detect_format(&format!("\u{feff}{}", JSON)), OrbitFormat::OmmJson);
+        assert_eq!(detect_format("CCSDS_OEM_VERS = 2.0\n"), OrbitFormat::Oem);
+        assert_eq!(detect_format("<?xml version=\"1.0\"?>\n<oem id=\"CCSDS_OEM_VERS\">"), OrbitFormat::Oem);
+        assert_eq!(detect_format("#dP2024  1  1  0  0  0.00000000      96 ORBIT IGS20 FIT  IGS\n"), OrbitFormat::Sp3);
+    }
+
+    #[test]
+    fn every_format_matches_the_3le() {
+        let tle = only_element(TLE);
+        for text in [JSON, XML, CSV, KVN] {
+            assert_same_elements(&only_element(text), &tle);
+        }
+    }
+
+    #[test]
+    fn json_numbers_may_be_strings() {
+        let quoted = JSON.replace("14.69819587", "\"14.69819587\"").replace("35683", "\"35683\"");
+        assert_same_elements(&only_element(&quoted), &only_element(TLE));
+    }
+
+    #[test]
+    fn bad_records_are_skipped_with_their_line() {
+        let json = format!("[{},\n{}]",
+                           JSON.trim_start_matches('[').trim_end_matches(']'),
+                           JSON.trim_start_matches('[').trim_end_matches(']').replace("\"MEAN_MOTION\": 14.69819587,", ""));
+        let set = parse_orbits(&json).unwrap();
+        assert_eq!(set.elements.len(), 1);
+        assert_eq!(set.errors.len(), 1);
+        assert_eq!(set.errors[0].line, 20);
+        assert_eq!(set.errors[0].name.as_deref(), Some("UK-DMC 2"));
+        assert!(set.errors[0].message.contains("MEAN_MOTION"), "{}", set.errors[0].message);
+
+        let csv = format!("{}{}", CSV, CSV.lines().nth(1).unwrap().replace("98.0221", "steep"));
+        let set = parse_orbits(&csv).unwrap();
+        assert_eq!(set.elements.len(), 1);
+        assert_eq!(set.errors[0].line, 3);
+        assert!(set.errors[0].message.contains("INCLINATION"), "{}", set.errors[0].message);
+
+        let kvn = format!("{}{}", KVN, KVN.replace("SGP4", "DSST"));
+        let set = parse_orbits(&kvn).unwrap();
+        assert_eq!(set.elements.len(), 1);
+        assert_eq!(set.errors[0].line, 24);
+        assert!(set.errors[0].message.contains("DSST"), "{}", set.errors[0].message);
+    }
+}
@@ -0,0 +1,44 @@
+//! purpose:
+//!     orbit_file.rs turns any orbit file satview reads into satellites: 2LE/3LE and OMM
+//!     (sgp4 elements, omm.rs) or the precise ephemeris formats OEM (oem.rs) and SP3 (sp3.rs).
+//!     The format is detected from the content, so the rest of the program does not care which it got.
+
+use std::path::Path;
+use crate::sat::omm::{detect_format, parse_orbits, OrbitFormat};
+use crate::sat::oem::parse_oem;
+use crate::sat::satellite::Satellite;
+use crate::sat::sp3::parse_sp3;
+use crate::sat::tle::TleError;
+
+/// Satellites loaded from an orbit file and the records that could not be read.
+/// Ephemeris files fail as a whole, so only element files have errors.
+pub struct SatelliteSet {
+    pub satellites: Vec<Satellite>,
+    pub errors: Vec<TleError>,
+}
+
+/// parse_orbit_file: satellites of a 2LE/3LE, OMM, OEM or SP3 text, detecting the format.
+pub fn parse_orbit_file(text: &str) -> anyhow::Result<SatelliteSet> {
+    let ephemerides = match detect_format(text) {
+        OrbitFormat::Oem => parse_oem(text)?,
+        OrbitFormat::Sp3 => parse_sp3(text)?,
+        _ => {
+            let set = parse_orbits(text)?;
+            return Ok(SatelliteSet {
+                satellites: set.elements.into_iter().map(Satellite::new).collect(),
+                errors: set.errors,
+            });
+        }
+    };
+    Ok(SatelliteSet {
+        satellites: ephemerides.into_iter().map(Satellite::from_ephemeris).collect(),
+        errors: Vec::new(),
+    })
+}
+
+/// load_orbit_file: reads and parses an orbit file, see parse_orbit_file.
+pub fn load_orbit_file(path: &Path) -> anyhow::Result<SatelliteSet> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("could not read orbit file {}: {}", path.display(), err))?;
+    parse_orbit_file(&text).map_err(|err| anyhow::anyhow!("{}: {}", path.display(), err))
+}
@@ -0,0 +1,157 @@
+//! purpose:
+//!     propagator.rs defines Propagator, what a Satellite asks for its state at a time, and its sgp4
+//!     implementation. The others are the tabulated Ephemeris (ephemeris.rs), two-body Kepler motion
+//!     (twobody.rs) and a numerical integrator with the J2 to J4 zonal terms (numerical.rs).
+//!     States are TEME, km and km/s, at UTC times. The two-body and numerical propagators treat
+//!     TEME as inertial, which over days is far below the differences between the models.
+
+use chrono::{NaiveDateTime, TimeDelta};
+use sgp4::Prediction;
+use crate::sat::ephemeris::Ephemeris;
+
+/// Earth gravitational parameter (WGS-84) of the two-body and numerical propagators, km^3/s^2.
+pub const MU: f64 = 398600.4418;
+
+/// Source of the TEME state of a satellite at any time.
+pub trait Propagator {
+    /// name: the name the satellite is reported with.
+    fn name(&self) -> String;
+
+    /// epoch: the epoch of the elements or initial state, the first sample of an ephemeris.
+    fn epoch(&self) -> NaiveDateTime;
+
+    /// state_at: TEME position (km) and velocity (km/s) at a UTC time.
+    fn state_at(&mut self, time: &NaiveDateTime) -> anyhow::Result<Prediction>;
+
+    /// orbital_period: time of one revolution.
+    fn orbital_period(&self) -> anyhow::Result<TimeDelta>;
+
+    /// valid_span: first and last time the propagator can give a state for, None when unlimited.
+    fn valid_span(&self) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        None
+    }
+
+    /// elements: the sgp4 elements (a TLE or OMM) behind the states, if any. Only these have a TLE age.
+    fn elements(&self) -> Option<&sgp4::Elements> {
+        None
+    }
+
+    /// ephemeris_mut: the tabulated ephemeris behind the states, if any, to change its interpolation.
+    fn ephemeris_mut(&mut self) -> Option<&mut Ephemeris> {
+        None
+    }
+}
+
+/// SGP4/SDP4 propagation of TLE or OMM elements. The constants are built on the first call and
+/// boxed as they are large.
+pub struct Sgp4Propagator {
+    elements: sgp4::Elements,
+    constants: Option<Box<sgp4::Constants>>,
+}
+
+impl Sgp4Propagator {
+    pub fn new(elements: sgp4::Elements) -> Sgp4Propagator {
+        Sgp4Propagator { elements, constants: None }
+    }
+}
+
+impl Propagator for Sgp4Propagator {
+    /// name: the 3LE name line, the NORAD id for 2LE records.
+    fn name(&self) -> String {
+        self.elements.object_name.clone()
+            .unwrap_or_else(|| self.elements.norad_id.to_string())
+    }
+
+    fn epoch(&self) -> NaiveDateTime {
+        self.elements.datetime
+    }
+
+    fn state_at(&mut self, time: &NaiveDateTime) -> anyhow::Result<Prediction> {
+        let minutes = self.elements.datetime_to_minutes_since_epoch(time)?;
+        let constants = match self.constants {
+            Some(ref constants) => constants,
+            None => self.constants.insert(Box::new(sgp4::Constants::from_elements(&self.elements)?)),
+        };
+        Ok(constants.propagate(minutes)?)
+    }
+
+    /// orbital_period: from the mean motion.
+    fn orbital_period(&self) -> anyhow::Result<TimeDelta> {
+        Ok(period(86_400.0 / self.elements.mean_motion))
+    }
+
+    fn elements(&self) -> Option<&sgp4::Elements> {
+        Some(&self.elements)
+    }
+}
+
+impl Propagator for Ephemeris {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn epoch(&self) -> NaiveDateTime {
+        self.start()
+    }
+
+    fn state_at(&mut self, time: &NaiveDateTime) -> anyhow::Result<Prediction> {
+        Ephemeris::state_at(self, time)
+    }
+
+    fn orbital_period(&self) -> anyhow::Result<TimeDelta> {
+        Ephemeris::orbital_period(self)
+    }
+
+    fn valid_span(&self) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        Some((self.start(), self.end()))
+    }
+
+    fn ephemeris_mut(&mut self) -> Option<&mut Ephemeris> {
+        Some(self)
+    }
+}
+
+/// osculating_period: two-body period of the orbit through a TEME state, fails when it is not closed.
+pub fn osculating_period(position: &[f64; 3], velocity: &[f64; 3]) -> anyhow::Result<TimeDelta> {
+    let r = norm(position);
+    let v = norm(velocity);
+    let semi_major_axis = 1.0 / (2.0 / r - v * v / MU);
+    if semi_major_axis.is_nan() || semi_major_axis <= 0.0 {
+        anyhow::bail!("a state at {:.1} km moving at {:.3} km/s is not on a closed orbit", r, v);
+    }
+    Ok(period(2.0 * std::f64::consts::PI * (semi_major_axis.powi(3) / MU).sqrt()))
+}
+
+/// period: an orbital period in seconds to the nearest millisecond.
+fn period(seconds: f64) -> TimeDelta {
+    TimeDelta::milliseconds((seconds * 1000.0).round() as i64)
+}
+
+pub(crate) fn norm(vector: &[f64; 3]) -> f64 {
+    (vector[0] * vector[0] + vector[1] * vector[1] + vector[2] * vector[2]).sqrt()
+}
+
+pub(crate) fn dot(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sat::tle::parse_tles;
+
+    #[test]
+    fn sgp4_period_keeps_milliseconds() {
+        // UK-DMC 2 at 14.69819587 rev/day.
+        let propagator = Sgp4Propagator::new(parse_tles(include_str!("../../common/tle2.txt")).elements.remove(0));
+        assert_eq!(propagator.orbital_period().unwrap(), TimeDelta::milliseconds(5_878_272));
+    }
+
+    #[test]
+    fn osculating_period_rounds_to_the_millisecond() {
+        // Circular at 7000 km: 5828.5166 s.
+        let speed = (MU / 7000.0).sqrt();
+        assert_eq!(osculating_period(&[7000.0, 0.0, 0.0], &[0.0, speed, 0.0]).unwrap(), TimeDelta::milliseconds(5_828_517));
+        assert!(osculating_period(&[7000.0, 0.0, 0.0], &[0.0, speed * 1.5, 0.0]).is_err());
+    }
+}
@@ -1,30 +1,160 @@
 use sgp4::Prediction;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, TimeDelta};
 use crate::coordinate_systems::{TEME, Geodetic, ECEF};
+use crate::sat::ephemeris::Ephemeris;
+use crate::sat::propagator::{Propagator, Sgp4Propagator};
+use crate::sat::staleness::{days, StalenessAction, StalenessPolicy};
+use crate::utils::satutils::{ecef_to_geodetic, teme_to_ecef, to_sidereal};
 
 /// A satellite should just be a place to store satellite information
 /// coordinate Geodetic and TEME + sidereal
 /// Update methods, etc...
-pub struct Satellite { 
+/// epoch is the time the coordinates were last propagated to.
+/// tle_age is epoch minus the TLE epoch, staleness says what to do when it gets too large.
+/// The propagator gives the states: sgp4 for TLE/OMM elements, an ephemeris, two-body or numerical.
+/// Satellites without sgp4 elements have no TLE age and are never stale.
+pub struct Satellite {
     pub geodetic_coordinates: Geodetic,
     pub teme_coordinates: TEME,
     pub ecef_coordinates: ECEF,
-    pub sat_elements: sgp4::Elements,
+    pub epoch: NaiveDateTime,
+    pub tle_age: Option<TimeDelta>,
+    pub staleness: StalenessPolicy,
+    pub propagator: Box<dyn Propagator>,
+    age_range: Option<(TimeDelta, TimeDelta)>,
+}
+
+/// A prediction (TEME, km and km/s) with the age of the TLE it was made from, None without sgp4 elements.
+#[derive(Debug, Clone)]
+pub struct AgedPrediction {
+    pub prediction: Prediction,
+    pub tle_age: Option<TimeDelta>,
 }
 
 impl Satellite {
-    pub fn new(sat_elements: sgp4::Elements) -> Satellite {        
-        Satellite { 
-            geodetic_coordinates: Geodetic { 
+    pub fn new(sat_elements: sgp4::Elements) -> Satellite {
+        Satellite::with_propagator(Box::new(Sgp4Propagator::new(sat_elements)))
+    }
+
+    /// from_ephemeris: a satellite interpolating a tabulated ephemeris.
+    pub fn from_ephemeris(ephemeris: Ephemeris) -> Satellite {
+        Satellite::with_propagator(Box::new(ephemeris))
+    }
+
+    /// with_propagator: a satellite moved by any propagator.
+    pub fn with_propagator(propagator: Box<dyn Propagator>) -> Satellite {
+        let epoch = propagator.epoch();
+        Satellite {
+            geodetic_coordinates: Geodetic {
                 ..Default::default()
             },
-            teme_coordinates: TEME { 
+            teme_coordinates: TEME {
                 ..Default::default()
             },
-            ecef_coordinates: ECEF { 
+            ecef_coordinates: ECEF {
                 ..Default::default()
             },
-            sat_elements: sat_elements,
+            epoch,
+            tle_age: None,
+            staleness: StalenessPolicy::default(),
+            propagator,
+            age_range: None,
+        }
+    }
+
+    /// propagate: moves the satellite to an absolute UTC time.
+    /// Fills in teme_coordinates, ecef_coordinates, geodetic_coordinates and tle_age.
+    /// Fails when the TLE is stale at that time and the staleness action is Refuse,
+    /// or when the time is outside the span of an ephemeris.
+    /// input: UTC time
+    /// output: the prediction (TEME, km and km/s) and the TLE age
+    pub fn propagate(&mut self, new_epoch: &NaiveDateTime) -> anyhow::Result<AgedPrediction> {
+        let tle_age = self.tle_age_at(new_epoch);
+        if let Some(age) = tle_age {
+            if self.staleness.action == StalenessAction::Refuse && self.staleness.is_stale(&age) {
+                anyhow::bail!("{} TLE epoch {} is {:.1} days from {}, more than the {:.1} day limit",
+                              self.name(), self.reference_epoch().format("%Y-%m-%d %H:%M:%S"), days(&age).abs(), new_epoch,
+                              days(&self.staleness.max_age));
+            }
+        }
+
+        let prediction = self.propagator.state_at(new_epoch)?;
+        self.update_sat_state(&prediction, new_epoch);
+        Ok(AgedPrediction { prediction, tle_age })
+    }
+
+    /// update_sat_state: sets the satellite coordinates for all reference frames.
+    /// input: sgp4 prediction (TEME), epoch of the prediction
+    pub fn update_sat_state(&mut self, sat_prediction: &Prediction, new_epoch: &NaiveDateTime) {
+        let pos = sat_prediction.position;
+        let velo = sat_prediction.velocity;
+
+        self.teme_coordinates = TEME {
+            pos_vector: pos,
+            pos_magnitude: (pos[0] * pos[0] + pos[1] * pos[1] + pos[2] * pos[2]).sqrt(),
+            velo_vector: velo,
+            velo_magnitude: (velo[0] * velo[0] + velo[1] * velo[1] + velo[2] * velo[2]).sqrt(),
+            sidereal: to_sidereal(new_epoch),
+        };
+        self.ecef_coordinates = teme_to_ecef(&self.teme_coordinates, new_epoch);
+        self.geodetic_coordinates = ecef_to_geodetic(&self.ecef_coordinates);
+        self.epoch = *new_epoch;
+        self.tle_age = self.tle_age_at(new_epoch);
+        if let Some(age) = self.tle_age {
+            self.age_range = Some(match self.age_range {
+                Some((min, max)) => (min.min(age), max.max(age)),
+                None => (age, age),
+            });
+        }
+    }
+
+    /// name: the 3LE name line, the NORAD id for 2LE records, the object name of an ephemeris.
+    pub fn name(&self) -> String {
+        self.propagator.name()
+    }
+
+    /// elements: the sgp4 elements, None for other propagators.
+    pub fn elements(&self) -> Option<&sgp4::Elements> {
+        self.propagator.elements()
+    }
+
+    /// reference_epoch: the TLE epoch, the first sample of an ephemeris or the initial state's epoch.
+    pub fn reference_epoch(&self) -> NaiveDateTime {
+        self.propagator.epoch()
+    }
+
+    /// valid_span: first and last time the propagator covers (an ephemeris), None when unlimited.
+    pub fn valid_span(&self) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        self.propagator.valid_span()
+    }
+
+    /// orbital_period: time of one revolution, from the mean motion or the osculating orbit.
+    pub fn orbital_period(&self) -> anyhow::Result<TimeDelta> {
+        self.propagator.orbital_period()
+    }
+
+    /// tle_age_at: time minus the TLE epoch, None without sgp4 elements.
+    pub fn tle_age_at(&self, time: &NaiveDateTime) -> Option<TimeDelta> {
+        self.elements().map(|elements| *time - elements.datetime)
+    }
+
+    /// age_range: smallest and largest TLE age of all predictions so far, None before the first
+    /// and without sgp4 elements.
+    pub fn age_range(&self) -> Option<(TimeDelta, TimeDelta)> {
+        self.age_range
+    }
+
+    /// is_stale: whether any prediction so far used a stale TLE.
+    pub fn is_stale(&self) -> bool {
+        self.age_range.is_some_and(|(min, max)| self.staleness.is_stale(&min) || self.staleness.is_stale(&max))
+    }
+
+    /// elevation_margin: extra elevation mask in degrees for visibility checks between start and end,
+    /// taken at the end of the range farthest from the TLE epoch. Always 0 without sgp4 elements.
+    pub fn elevation_margin(&self, start: &NaiveDateTime, end: &NaiveDateTime) -> f64 {
+        match (self.tle_age_at(start), self.tle_age_at(end)) {
+            (Some(start_age), Some(end_age)) => self.staleness.elevation_margin(&start_age.abs().max(end_age.abs())),
+            _ => 0.0,
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,145 @@
+//! purpose:
+//!     sp3.rs loads SP3 (versions a to d) precise orbit files, the format of the IGS and GNSS
+//!     analysis centres. Every satellite in the file becomes one Ephemeris.
+//!     Positions are km and velocities dm/s in an earth-fixed frame (IGSxx/ITRFxx); epochs are in the time
+//!     system of the first %c line (GPS when it is not given). Both are converted to UTC and TEME.
+//!     Positions flagged as bad (0.000000) are skipped.
+
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+use crate::sat::ephemeris::{Ephemeris, Interpolation, ReferenceFrame, StateSample, TimeSystem, DEFAULT_LAGRANGE_POINTS};
+
+/// Velocity unit of SP3 V records, dm/s, in km/s.
+const DECIMETRES_PER_SECOND: f64 = 1e-4;
+
+/// parse_sp3: one Lagrange interpolated ephemeris per satellite id (G01, E12, ...), in order of first appearance.
+pub fn parse_sp3(text: &str) -> anyhow::Result<Vec<Ephemeris>> {
+    let mut time_system = None;
+    let mut epoch: Option<NaiveDateTime> = None;
+    // Satellite id and its states as given, time system and frame of the file.
+    let mut satellites: Vec<(String, Vec<StateSample>)> = Vec::new();
+
+    for (index, raw) in text.trim_start_matches('\u{feff}').lines().enumerate() {
+        let number = index + 1;
+        let line = raw.trim_end();
+        if line.starts_with("%c") && time_system.is_none() {
+            let name = line.get(9..12).unwrap_or("").trim();
+            time_system = Some(match name {
+                "" | "ccc" => TimeSystem::Gps,
+                name => TimeSystem::parse(name).map_err(|err| anyhow::anyhow!("line {}: {}", number, err))?,
+            });
+        } else if let Some(epoch_line) = line.strip_prefix('*') {
+            epoch = Some(parse_epoch(epoch_line).map_err(|err| anyhow::anyhow!("line {}: {}", number, err))?);
+        } else if line.starts_with('P') || line.starts_with('V') {
+            let time = epoch.ok_or_else(|| anyhow::anyhow!("line {}: state before the first epoch line", number))?;
+            let id = line.get(1..4).unwrap_or("").trim().to_string();
+            let vector = parse_vector(line).map_err(|err| anyhow::anyhow!("line {}: {}", number, err))?;
+            if line.starts_with('P') {
+                if vector.iter().all(|value| *value == 0.0) {
+                    continue;
+                }
+                let index = match satellites.iter().position(|satellite| satellite.0 == id) {
+                    Some(index) => index,
+                    None => {
+                        satellites.push((id, Vec::new()));
+                        satellites.len() - 1
+                    }
+                };
+                satellites[index].1.push(StateSample { time, position: vector, velocity: None });
+            } else if let Some(state) = satellites.iter_mut()
+                .find(|satellite| satellite.0 == id)
+                .and_then(|satellite| satellite.1.last_mut())
+                .filter(|state| state.time == time) {
+                state.velocity = Some(vector.map(|value| value * DECIMETRES_PER_SECOND));
+            }
+        } else if line.starts_with("EOF") {
+            break;
+        }
+    }
+    if satellites.is_empty() {
+        anyhow::bail!("no satellite positions in the SP3 file");
+    }
+
+    let time_system = time_system.unwrap_or(TimeSystem::Gps);
+    satellites.into_iter()
+        .map(|(id, states)| {
+            let samples = states.into_iter()
+                .map(|state| {
+                    let time = time_system.to_utc(&state.time);
+                    let (position, velocity) = ReferenceFrame::EarthFixed.to_teme(&time, state.position, state.velocity);
+                    StateSample { time, position, velocity }
+                })
+                .collect();
+            Ephemeris::new(&id, samples, Interpolation::Lagrange, DEFAULT_LAGRANGE_POINTS)
+        })
+        .collect()
+}
+
+/// parse_epoch: "  2024  1  1  0  0  0.00000000" (after the '*') as a time.
+fn parse_epoch(text: &str) -> anyhow::Result<NaiveDateTime> {
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    if fields.len() != 6 {
+        anyhow::bail!("{:?} is not an epoch line", text.trim());
+    }
+    let whole = |index: usize| fields[index].parse::<u32>()
+        .map_err(|_| anyhow::anyhow!("{:?} is not a whole number", fields[index]));
+    let seconds: f64 = fields[5].parse().map_err(|_| anyhow::anyhow!("{:?} is not a number", fields[5]))?;
+    let (hour, minute) = (whole(3)?, whole(4)?);
+    NaiveDate::from_ymd_opt(whole(0)? as i32, whole(1)?, whole(2)?)
+        .and_then(|date| date.and_hms_opt(hour, minute, 0))
+        .map(|time| time + TimeDelta::nanoseconds((seconds * 1e9).round() as i64))
+        .ok_or_else(|| anyhow::anyhow!("{:?} is not a valid date", text.trim()))
+}
+
+/// parse_vector: the x, y, z columns of a P or V record.
+fn parse_vector(line: &str) -> anyhow::Result<[f64; 3]> {
+    let mut vector = [0.0; 3];
+    for (axis, value) in vector.iter_mut().enumerate() {
+        let field = line.get(4 + 14 * axis..18 + 14 * axis).unwrap_or("").trim();
+        *value = field.parse().map_err(|_| anyhow::anyhow!("{:?} is not a number", field))?;
+    }
+    Ok(vector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinate_systems::TEME;
+    use crate::sat::satellite::Satellite;
+    use crate::sat::tle::parse_tles;
+    use crate::utils::satutils::{teme_to_ecef, teme_to_ecef_velocity};
+
+    #[test]
+    fn positions_and_velocities_round_trip() {
+        let mut satellite = Satellite::new(parse_tles(include_str!("../../common/tle2.txt")).elements.remove(0));
+        let start = NaiveDate::from_ymd_opt(2012, 10, 15).unwrap().and_hms_opt(6, 0, 0).unwrap();
+        let mut text = String::from("#dV2012 10 15  6  0 16.00000000      12 ORBIT IGS14 HLM  TEST\n\
+                                     %c M  cc GPS ccc cccc cccc cccc cccc ccccc ccccc ccccc ccccc\n");
+        let mut truth = Vec::new();
+        for index in 0..12 {
+            let time = start + TimeDelta::minutes(5 * index);
+            let state = satellite.propagate(&time).unwrap().prediction;
+            let teme = TEME { pos_vector: state.position, velo_vector: state.velocity, ..Default::default() };
+            let (r, v) = (teme_to_ecef(&teme, &time), teme_to_ecef_velocity(&teme, &time));
+            // GPS time is UTC + 16 s in 2012.
+            text += &format!("*  {}\n", (time + TimeDelta::seconds(16)).format("%Y %_m %_d %_H %_M %S.00000000"));
+            text += &format!("PL51{:14.6}{:14.6}{:14.6}    999.999999\n", r.x, r.y, r.z);
+            text += &format!("VL51{:14.6}{:14.6}{:14.6}    999.999999\n", v[0] * 1e4, v[1] * 1e4, v[2] * 1e4);
+            text += "PL52      0.000000      0.000000      0.000000 999999.999999\n";
+            truth.push((time, state));
+        }
+        text += "EOF\n";
+
+        let ephemerides = parse_sp3(&text).unwrap();
+        assert_eq!(ephemerides.len(), 1);
+        let ephemeris = &ephemerides[0];
+        assert_eq!(ephemeris.name, "L51");
+        assert_eq!(ephemeris.start(), start);
+        assert!(ephemeris.samples().iter().all(|sample| sample.velocity.is_some()));
+        let (time, expected) = &truth[5];
+        let state = ephemeris.state_at(time).unwrap();
+        for axis in 0..3 {
+            assert!((state.position[axis] - expected.position[axis]).abs() < 1e-5, "{:?} vs {:?}", state.position, expected.position);
+            assert!((state.velocity[axis] - expected.velocity[axis]).abs() < 1e-4, "{:?} vs {:?}", state.velocity, expected.velocity);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! purpose:
+//!     staleness.rs decides what to do when a satellite is propagated far from its TLE epoch.
+//!     SGP4 errors grow with the time from epoch (roughly a few km per day in LEO), so a
+//!     prediction made with an old TLE is less accurate than it looks.
+//!     The TLE age is the requested time minus the TLE epoch, negative before the epoch.
+
+use chrono::TimeDelta;
+
+/// Age above which a TLE is considered stale by default.
+pub const DEFAULT_MAX_TLE_AGE_DAYS: i64 = 14;
+
+/// What to do with a prediction whose TLE is older than the limit.
+/// Warn: propagate normally, the age is recorded and reported.
+/// Refuse: propagation fails.
+/// WidenMargin: propagate normally, visibility checks raise the elevation mask by
+/// margin_per_day for every day over the limit.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StalenessAction {
+    Warn,
+    Refuse,
+    WidenMargin,
+}
+
+/// Staleness policy of a satellite.
+/// max_age applies to both sides of the epoch, margin_per_day is in degrees per day.
+#[derive(Debug, Copy, Clone)]
+pub struct StalenessPolicy {
+    pub max_age: TimeDelta,
+    pub action: StalenessAction,
+    pub margin_per_day: f64,
+}
+
+impl Default for StalenessPolicy {
+    fn default() -> StalenessPolicy {
+        StalenessPolicy {
+            max_age: TimeDelta::days(DEFAULT_MAX_TLE_AGE_DAYS),
+            action: StalenessAction::Warn,
+            margin_per_day: 1.0,
+        }
+    }
+}
+
+impl StalenessPolicy {
+    pub fn is_stale(&self, age: &TimeDelta) -> bool {
+        age.abs() > self.max_age
+    }
+
+    /// elevation_margin: extra elevation mask in degrees for a TLE age,
+    /// zero unless the action is WidenMargin and the TLE is stale.
+    pub fn elevation_margin(&self, age: &TimeDelta) -> f64 {
+        if self.action != StalenessAction::WidenMargin || !self.is_stale(age) {
+            return 0.0;
+        }
+        days(&(age.abs() - self.max_age)) * self.margin_per_day
+    }
+}
+
+/// days: a duration in fractional days.
+pub fn days(duration: &TimeDelta) -> f64 {
+    duration.num_milliseconds() as f64 / 86_400_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sat::satellite::Satellite;
+    use crate::sat::tle::parse_tles;
+
+    #[test]
+    fn stale_only_past_the_limit_on_either_side() {
+        let limit = TimeDelta::days(DEFAULT_MAX_TLE_AGE_DAYS);
+        let widen = StalenessPolicy { action: StalenessAction::WidenMargin, margin_per_day: 2.0, ..Default::default() };
+        for sign in [1, -1] {
+            let at_limit = limit * sign;
+            let just_over = (limit + TimeDelta::milliseconds(1)) * sign;
+            assert!(!widen.is_stale(&at_limit));
+            assert!(widen.is_stale(&just_over));
+            assert_eq!(widen.elevation_margin(&at_limit), 0.0);
+            assert!((widen.elevation_margin(&((limit + TimeDelta::hours(36)) * sign)) - 3.0).abs() < 1e-12);
+        }
+        // Only WidenMargin raises the mask.
+        let warn = StalenessPolicy::default();
+        assert!(warn.is_stale(&TimeDelta::days(20)));
+        assert_eq!(warn.elevation_margin(&TimeDelta::days(20)), 0.0);
+    }
+
+    #[test]
+    fn refuse_fails_propagation_past_the_limit() {
+        let mut satellite = Satellite::new(parse_tles(include_str!("../../common/tle2.txt")).elements.remove(0));
+        satellite.staleness = StalenessPolicy { action: StalenessAction::Refuse, ..Default::default() };
+        let epoch = satellite.reference_epoch();
+        let limit = TimeDelta::days(DEFAULT_MAX_TLE_AGE_DAYS);
+        assert_eq!(satellite.propagate(&(epoch + limit)).unwrap().tle_age, Some(limit));
+        assert!(satellite.propagate(&(epoch - limit)).is_ok());
+        let error = satellite.propagate(&(epoch + limit + TimeDelta::days(1))).err().unwrap().to_string();
+        assert!(error.contains("15.0 days from") && error.ends_with("the 14.0 day limit"), "{}", error);
+    }
+}
@@ -0,0 +1,345 @@
+//! purpose:
+//!     tle.rs loads two-line element sets from text. Files may mix 2LE and 3LE records
+//!     (the name line is optional, "0 NAME" as written by Space-Track is accepted too),
+//!     contain blank lines, '#' comment lines and CRLF line endings.
+//!     Every line is checked for length and checksum before the elements are parsed.
+//!     A bad record is reported with its line number and skipped, the rest still load.
+//!     format_tle and write_tles go the other way, elements to fixed-column lines with checksums.
+
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use chrono::{Datelike, NaiveDateTime, Timelike};
+
+/// Length of a TLE line including the checksum digit.
+pub const TLE_LINE_LENGTH: usize = 69;
+
+/// A record that could not be loaded.
+/// line is the 1-based number of the offending line, name the record's name line if it had one.
+#[derive(Debug, Clone)]
+pub struct TleError {
+    pub line: usize,
+    pub name: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for TleError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(formatter, "line {} ({}): {}", self.line, name, self.message),
+            None => write!(formatter, "line {}: {}", self.line, self.message),
+        }
+    }
+}
+
+/// Result of loading a TLE (or OMM, see omm.rs) file: the records that parsed, in file order, and the ones that did not.
+pub struct TleSet {
+    pub elements: Vec<sgp4::Elements>,
+    pub errors: Vec<TleError>,
+}
+
+/// tle_checksum: the TLE checksum of the first 68 characters of a line,
+/// the sum of all digits with '-' counting as 1, modulo 10.
+pub fn tle_checksum(line: &str) -> u32 {
+    line.chars()
+        .take(TLE_LINE_LENGTH - 1)
+        .map(|character| match character {
+            '-' => 1,
+            _ => character.to_digit(10).unwrap_or(0),
+        })
+        .sum::<u32>() % 10
+}
+
+/// parse_tles: loads every record of a 2LE/3LE text.
+/// output: the parsed elements and one error per skipped record
+pub fn parse_tles(text: &str) -> TleSet {
+    let mut elements = Vec::new();
+    let mut errors = Vec::new();
+    // Pending name line and line 1, with their line numbers.
+    let mut name: Option<(usize, String)> = None;
+    let mut line1: Option<(usize, String)> = None;
+
+    for (index, raw) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = raw.trim_start_matches('\u{feff}').trim_end();
+        if line.trim_start().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with("1 ") {
+            if let Some((line1_number, _)) = line1.take() {
+                errors.push(record_error(line1_number, name.take(), "line 1 is not followed by a line 2"));
+            }
+            line1 = Some((number, line.to_string()));
+        } else if line.starts_with("2 ") {
+            match line1.take() {
+                Some((line1_number, first)) => {
+                    let record_name = name.take().map(|(_, name)| name);
+                    match parse_record(record_name.clone(), (line1_number, &first), (number, line)) {
+                        Ok(element) => elements.push(element),
+                        Err((line, message)) => errors.push(TleError { line, name: record_name, message }),
+                    }
+                }
+                None => errors.push(record_error(number, name.take(), "line 2 without a line 1 before it")),
+            }
+        } else {
+            if let Some((line1_number, _)) = line1.take() {
+                errors.push(record_error(line1_number, name.take(), "line 1 is not followed by a line 2"));
+            }
+            if let Some((name_number, orphan)) = name.take() {
+                errors.push(record_error(name_number, Some((name_number, orphan)), "name line is not followed by TLE lines"));
+            }
+            let record_name = line.strip_prefix("0 ").unwrap_or(line).trim();
+            name = Some((number, record_name.to_string()));
+        }
+    }
+
+    if let Some((line1_number, _)) = line1.take() {
+        errors.push(record_error(line1_number, name.take(), "line 1 is not followed by a line 2"));
+    }
+    if let Some((name_number, orphan)) = name.take() {
+        errors.push(record_error(name_number, Some((name_number, orphan)), "name line is not followed by TLE lines"));
+    }
+    errors.sort_by_key(|error| error.line);
+    TleSet { elements, errors }
+}
+
+/// load_tles: reads and parses a 2LE/3LE file. Only an unreadable file is an error,
+/// bad records are returned in TleSet::errors.
+pub fn load_tles(path: &Path) -> anyhow::Result<TleSet> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("could not read TLE file {}: {}", path.display(), err))?;
+    Ok(parse_tles(&text))
+}
+
+fn record_error(line: usize, name: Option<(usize, String)>, message: &str) -> TleError {
+    TleError {
+        line,
+        name: name.map(|(_, name)| name),
+        message: message.to_string(),
+    }
+}
+
+/// parse_record: checks both lines and parses the elements.
+/// input: name, (line number, text) of line 1 and line 2
+/// output: elements, or the number of the offending line and what is wrong with it
+fn parse_record(name: Option<String>,
+                line1: (usize, &str),
+                line2: (usize, &str)) -> Result<sgp4::Elements, (usize, String)> {
+    check_line(line1.1, 1).map_err(|message| (line1.0, message))?;
+    check_line(line2.1, 2).map_err(|message| (line2.0, message))?;
+    sgp4::Elements::from_tle(name, line1.1.as_bytes(), line2.1.as_bytes())
+        .map_err(|err| (line1.0, err.to_string()))
+}
+
+/// check_line: length, character set and checksum of one TLE line.
+fn check_line(line: &str, line_number: u8) -> Result<(), String> {
+    if !line.is_ascii() {
+        return Err(format!("line {} contains non-ASCII characters", line_number));
+    }
+    if line.len() != TLE_LINE_LENGTH {
+        return Err(format!("line {} is {} characters long, expected {}", line_number, line.len(), TLE_LINE_LENGTH));
+    }
+    let checksum = tle_checksum(line);
+    match line[TLE_LINE_LENGTH - 1..].chars().next().and_then(|character| character.to_digit(10)) {
+        Some(digit) if digit == checksum => Ok(()),
+        Some(digit) => Err(format!("line {} checksum is {} but the line ends in {}", line_number, checksum, digit)),
+        None => Err(format!("line {} does not end in a checksum digit", line_number)),
+    }
+}
+
+/// format_tle: the two lines of a TLE for elements, 69 columns each and ending in their checksum.
+/// Fields are rounded to the precision of their columns. The international designator is written
+/// back from sgp4's YYYY-NNNP form, NORAD ids above 99999 (Alpha-5) are not supported.
+/// output: line 1, line 2
+pub fn format_tle(elements: &sgp4::Elements) -> anyhow::Result<(String, String)> {
+    if elements.norad_id > 99_999 {
+        anyhow::bail!("NORAD id {} does not fit the 5 TLE columns", elements.norad_id);
+    }
+    let classification = match elements.classification {
+        sgp4::Classification::Unclassified => 'U',
+        sgp4::Classification::Classified => 'C',
+        sgp4::Classification::Secret => 'S',
+    };
+    let line1 = format!("1 {:05}{} {:<8} {} {} {} {} {} {:>4}",
+                        elements.norad_id,
+                        classification,
+                        designator_field(elements.international_designator.as_deref())?,
+                        epoch_field(&elements.datetime)?,
+                        decimal_field(elements.mean_motion_dot)?,
+                        exponent_field(elements.mean_motion_ddot)?,
+                        exponent_field(elements.drag_term)?,
+                        elements.ephemeris_type % 10,
+                        elements.element_set_number % 10_000);
+
+    if !(0.0..=180.0).contains(&elements.inclination) {
+        anyhow::bail!("inclination {} is outside 0 to 180 degrees", elements.inclination);
+    }
+    let eccentricity = (elements.eccentricity * 1e7).round();
+    if !(0.0..1e7).contains(&eccentricity) {
+        anyhow::bail!("eccentricity {} is outside 0 to 1", elements.eccentricity);
+    }
+    let mean_motion = format!("{:11.8}", elements.mean_motion);
+    if elements.mean_motion <= 0.0 || mean_motion.len() != 11 {
+        anyhow::bail!("mean motion {} rev/day does not fit the TLE columns", elements.mean_motion);
+    }
+    let line2 = format!("2 {:05} {:8.4} {} {:07} {} {} {}{:5}",
+                        elements.norad_id,
+                        elements.inclination,
+                        angle_field(elements.right_ascension),
+                        eccentricity as u32,
+                        angle_field(elements.argument_of_perigee),
+                        angle_field(elements.mean_anomaly),
+                        mean_motion,
+                        elements.revolution_number % 100_000);
+
+    Ok((with_checksum(line1), with_checksum(line2)))
+}
+
+/// write_tles: writes elements as 3LE, the name line is left out (2LE) for elements without a name.
+pub fn write_tles<W: Write>(writer: &mut W, elements: &[sgp4::Elements]) -> anyhow::Result<()> {
+    for element in elements {
+        let (line1, line2) = format_tle(element)?;
+        if let Some(name) = &element.object_name {
+            writeln!(writer, "{}", name)?;
+        }
+        writeln!(writer, "{}", line1)?;
+        writeln!(writer, "{}", line2)?;
+    }
+    Ok(())
+}
+
+fn with_checksum(line: String) -> String {
+    let checksum = tle_checksum(&line);
+    format!("{}{}", line, checksum)
+}
+
+/// designator_field: "2009-041C" to "09041C", blank without a designator.
+fn designator_field(designator: Option<&str>) -> anyhow::Result<String> {
+    let Some(designator) = designator else {
+        return Ok(String::new());
+    };
+    match designator.split_once('-') {
+        Some((year, piece)) if year.len() == 4 && year.chars().all(|c| c.is_ascii_digit())
+            && (4..=6).contains(&piece.len()) && piece.is_ascii() => Ok(format!("{}{}", &year[2..], piece)),
+        _ => anyhow::bail!("international designator {:?} is not like 2009-041C", designator),
+    }
+}
+
+/// epoch_field: YYDDD.DDDDDDDD, day of year with the fraction of the day.
+fn epoch_field(datetime: &NaiveDateTime) -> anyhow::Result<String> {
+    if !(1957..=2056).contains(&datetime.year()) {
+        anyhow::bail!("epoch {} is outside the 1957 to 2056 range of two digit TLE years", datetime);
+    }
+    let seconds = datetime.num_seconds_from_midnight() as f64 + datetime.nanosecond() as f64 / 1e9;
+    Ok(format!("{:02}{:012.8}", datetime.year() % 100, datetime.ordinal() as f64 + seconds / 86400.0))
+}
+
+/// decimal_field: a value below 1 as sign and .NNNNNNNN, e.g. " .00000484".
+fn decimal_field(value: f64) -> anyhow::Result<String> {
+    let digits = format!("{:.8}", value.abs());
+    match digits.strip_prefix('0') {
+        Some(fraction) => Ok(format!("{}{}", if value < 0.0 && fraction != ".00000000" { '-' } else { ' ' }, fraction)),
+        None => anyhow::bail!("mean motion derivative {} does not fit the TLE columns", value),
+    }
+}
+
+/// exponent_field: sign, five digit mantissa with an assumed leading decimal point and exponent,
+/// e.g. 0.89219e-4 as " 89219-4". Values too small for the columns are written as zero.
+fn exponent_field(value: f64) -> anyhow::Result<String> {
+    if value == 0.0 {
+        return Ok(" 00000-0".to_string());
+    }
+    let mut exponent = value.abs().log10().floor() as i32 + 1;
+    let mut mantissa = (value.abs() / 10f64.powi(exponent) * 1e5).round() as u32;
+    if mantissa >= 100_000 {
+        mantissa /= 10;
+        exponent += 1;
+    }
+    if exponent < -9 {
+        return Ok(" 00000-0".to_string());
+    }
+    if exponent > 9 {
+        anyhow::bail!("{} does not fit the TLE exponent columns", value);
+    }
+    Ok(format!("{}{:05}{}{}",
+               if value < 0.0 { '-' } else { ' ' },
+               mantissa,
+               if exponent > 0 { '+' } else { '-' },
+               exponent.abs()))
+}
+
+/// angle_field: an angle wrapped to 0..360 in degrees, as NNN.NNNN.
+fn angle_field(degrees: f64) -> String {
+    let rounded = (degrees.rem_euclid(360.0) * 1e4).round() / 1e4;
+    format!("{:8.4}", if rounded >= 360.0 { 0.0 } else { rounded })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_lines(text: &str) -> Vec<&str> {
+        text.lines().filter(|line| line.starts_with("1 ") || line.starts_with("2 ")).collect()
+    }
+
+    #[test]
+    fn format_tle_reproduces_the_repo_tles() {
+        let text = include_str!("../../common/tle2.txt");
+        let elements = parse_tles(text).elements;
+        assert_eq!(elements.len() * 2, record_lines(text).len());
+        for (element, expected) in elements.iter().zip(record_lines(text).chunks(2)) {
+            let (line1, line2) = format_tle(element).unwrap();
+            assert_eq!(line1, expected[0]);
+            assert_eq!(line2, expected[1]);
+        }
+
+        // The synthetic MP TLEs write '+' signs and zero as 00000-1, so only line 2 is column for column.
+        let text = include_str!("../../common/tle.txt");
+        let elements = parse_tles(text).elements;
+        assert_eq!(elements.len() * 2, record_lines(text).len());
+        for (element, expected) in elements.iter().zip(record_lines(text).chunks(2)) {
+            assert_eq!(format_tle(element).unwrap().1, expected[1]);
+        }
+    }
+
+    #[test]
+    fn parse_tles_skips_bad_records_and_reports_their_lines() {
+        let good1 = "1 35683U 09041C   12289.23158813  .00000484  00000-0  89219-4 0  5863";
+        let good2 = "2 35683  98.0221 185.3682 0001499 100.5295 259.6088 14.69819587172294";
+        let bad2 = "2 35683  98.0221 185.3682 0001499 100.5295 259.6088 14.69819587172295";
+        let mp1 = "1 54755U 22174A   24219.70271814 -.00000027  00000-0  00000-0 0  9990";
+        let mp2 = "2 54755   0.0586 347.7780 0006735 143.9438 207.7361  5.00113948 31366";
+        let text = [
+            "# comment", "UK-DMC 2", good1, good2, "",      // lines 1-5: 3LE after a comment and before a blank line
+            "0 BROKEN", good1, bad2,                        // lines 6-8: checksum of line 2 is wrong
+            mp1, mp2,                                       // lines 9-10: 2LE
+            "ORPHAN",                                       // line 11: name line without a record
+            "MP01", mp1, mp2,                               // lines 12-14
+            good1,                                          // line 15: line 1 without a line 2
+        ].join("\r\n");
+
+        let set = parse_tles(&text);
+        let names: Vec<String> = set.elements.iter()
+            .map(|element| element.object_name.clone().unwrap_or_else(|| element.norad_id.to_string()))
+            .collect();
+        assert_eq!(names, ["UK-DMC 2", "54755", "MP01"]);
+
+        let errors: Vec<(usize, Option<&str>)> = set.errors.iter()
+            .map(|error| (error.line, error.name.as_deref()))
+            .collect();
+        assert_eq!(errors, [(8, Some("BROKEN")), (11, Some("ORPHAN")), (15, None)]);
+        assert!(set.errors[0].message.contains("checksum is 4 but the line ends in 5"), "{}", set.errors[0]);
+        assert!(set.errors[2].message.contains("not followed by a line 2"), "{}", set.errors[2]);
+    }
+
+    #[test]
+    fn exponent_fields() {
+        assert_eq!(exponent_field(0.89219e-4).unwrap(), " 89219-4");
+        assert_eq!(exponent_field(-0.11606e-4).unwrap(), "-11606-4");
+        assert_eq!(exponent_field(0.999996e-3).unwrap(), " 10000-2");
+        assert_eq!(exponent_field(0.0).unwrap(), " 00000-0");
+        assert_eq!(exponent_field(1e-20).unwrap(), " 00000-0");
+        assert!(exponent_field(1e12).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+//! purpose:
+//!     twobody.rs propagates an orbit as pure two-body (Kepler) motion around a point mass earth,
+//!     the reference the other propagators can be compared against and the quickest way to fly a
+//!     "what-if" orbit that has no TLE.
+//!     The state at any time follows from the initial state with the universal variable form of
+//!     Kepler's equation and the Lagrange f and g coefficients (Curtis, Orbital Mechanics for
+//!     Engineering Students, algorithms 3.3 and 3.4), which has no trouble with circular or equatorial orbits.
+
+use chrono::{NaiveDateTime, TimeDelta};
+use sgp4::Prediction;
+use crate::sat::keplerian::KeplerianElements;
+use crate::sat::propagator::{dot, norm, osculating_period, Propagator, MU};
+
+/// Newton iterations allowed for the universal anomaly.
+const MAX_ITERATIONS: usize = 50;
+
+/// Two-body motion from a TEME state (km, km/s) at an epoch (UTC).
+#[derive(Debug, Clone)]
+pub struct TwoBodyPropagator {
+    pub name: String,
+    pub epoch: NaiveDateTime,
+    pub position: [f64; 3],
+    pub velocity: [f64; 3],
+}
+
+impl TwoBodyPropagator {
+    pub fn new(name: &str, epoch: NaiveDateTime, position: [f64; 3], velocity: [f64; 3]) -> TwoBodyPropagator {
+        TwoBodyPropagator { name: name.to_string(), epoch, position, velocity }
+    }
+
+    /// from_keplerian: two-body motion of a planned satellite, see KeplerianElements::to_state.
+    pub fn from_keplerian(elements: &KeplerianElements) -> anyhow::Result<TwoBodyPropagator> {
+        let (position, velocity) = elements.to_state()?;
+        Ok(TwoBodyPropagator::new(&elements.name, elements.epoch, position, velocity))
+    }
+}
+
+impl Propagator for TwoBodyPropagator {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn epoch(&self) -> NaiveDateTime {
+        self.epoch
+    }
+
+    fn state_at(&mut self, time: &NaiveDateTime) -> anyhow::Result<Prediction> {
+        let seconds = (*time - self.epoch).num_nanoseconds()
+            .ok_or_else(|| anyhow::anyhow!("{} is too far from the epoch {}", time, self.epoch))? as f64 / 1e9;
+        let (position, velocity) = kepler_state(&self.position, &self.velocity, seconds)?;
+        Ok(Prediction { position, velocity })
+    }
+
+    fn orbital_period(&self) -> anyhow::Result<TimeDelta> {
+        osculating_period(&self.position, &self.velocity)
+    }
+}
+
+/// kepler_state: two-body state seconds after (or before) a TEME state.
+/// Closed orbits are first brought back to within one period of the initial state.
+pub fn kepler_state(position: &[f64; 3], velocity: &[f64; 3], seconds: f64) -> anyhow::Result<([f64; 3], [f64; 3])> {
+    let r0 = norm(position);
+    let radial_velocity = dot(position, velocity) / r0;
+    // Reciprocal of the semi-major axis, negative for hyperbolic orbits.
+    let alpha = 2.0 / r0 - dot(velocity, velocity) / MU;
+    let seconds = if alpha > 0.0 {
+        seconds.rem_euclid(2.0 * std::f64::consts::PI / (alpha.powi(3) * MU).sqrt())
+    } else {
+        seconds
+    };
+    let sqrt_mu = MU.sqrt();
+
+    // Newton iteration on the universal anomaly chi.
+    let mut chi = sqrt_mu * alpha.abs() * seconds;
+    let mut converged = false;
+    for _ in 0..MAX_ITERATIONS {
+        let z = alpha * chi * chi;
+        let (c, s) = stumpff(z);
+        let f = r0 * radial_velocity / sqrt_mu * chi * chi * c + (1.0 - alpha * r0) * chi.powi(3) * s + r0 * chi
+            - sqrt_mu * seconds;
+        let derivative = r0 * radial_velocity / sqrt_mu * chi * (1.0 - alpha * chi * chi * s)
+            + (1.0 - alpha * r0) * chi * chi * c + r0;
+        let step = f / derivative;
+        chi -= step;
+        if step.abs() <= 1e-12 * chi.abs().max(1.0) {
+            converged = true;
+            break;
+        }
+    }
+    if !converged {
+        anyhow::bail!("Kepler's equation did not converge {} s from the initial state", seconds);
+    }
+
+    let z = alpha * chi * chi;
+    let (c, s) = stumpff(z);
+    let f = 1.0 - chi * chi / r0 * c;
+    let g = seconds - chi.powi(3) * s / sqrt_mu;
+    let new_position = [
+        f * position[0] + g * velocity[0],
+        f * position[1] + g * velocity[1],
+        f * position[2] + g * velocity[2],
+    ];
+    let r = norm(&new_position);
+    let f_dot = sqrt_mu / (r * r0) * (alpha * chi.powi(3) * s - chi);
+    let g_dot = 1.0 - chi * chi / r * c;
+    let new_velocity = [
+        f_dot * position[0] + g_dot * velocity[0],
+        f_dot * position[1] + g_dot * velocity[1],
+        f_dot * position[2] + g_dot * velocity[2],
+    ];
+    Ok((new_position, new_velocity))
+}
+
+/// stumpff: the Stumpff functions C(z) and S(z), by their series near 0.
+fn stumpff(z: f64) -> (f64, f64) {
+    if z.abs() < 1e-6 {
+        (0.5 - z / 24.0, 1.0 / 6.0 - z / 120.0)
+    } else if z > 0.0 {
+        let root = z.sqrt();
+        ((1.0 - root.cos()) / z, (root - root.sin()) / root.powi(3))
+    } else {
+        let root = (-z).sqrt();
+        ((root.cosh() - 1.0) / -z, (root.sinh() - root) / root.powi(3))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn elements(eccentricity: f64, inclination: f64, mean_anomaly: f64) -> KeplerianElements {
+        KeplerianElements {
+            name: "MP01".to_string(),
+            norad_id: 99901,
+            international_designator: None,
+            epoch: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap(),
+            semi_major_axis: 14_446.256,
+            eccentricity,
+            inclination,
+            right_ascension: 40.0,
+            argument_of_perigee: 300.0,
+            mean_anomaly,
+            drag_term: 0.0,
+        }
+    }
+
+    #[test]
+    fn kepler_motion_advances_the_mean_anomaly() {
+        // An equatorial circular orbit (the MP ring) and an inclined eccentric one.
+        for (eccentricity, inclination) in [(0.0, 0.0), (0.3, 63.4)] {
+            let start = elements(eccentricity, inclination, 10.0);
+            let mut propagator = TwoBodyPropagator::from_keplerian(&start).unwrap();
+            let mean_motion = (MU / start.semi_major_axis.powi(3)).sqrt().to_degrees();
+            // Half a revolution and 7.25 days (many revolutions) on, and a day back.
+            for seconds in [0.5 * 360.0 / mean_motion, 7.25 * 86400.0, -86400.0] {
+                let time = start.epoch + TimeDelta::milliseconds((seconds * 1000.0) as i64);
+                let state = propagator.state_at(&time).unwrap();
+                let elapsed = (time - start.epoch).num_milliseconds() as f64 / 1000.0;
+                let later = KeplerianElements { mean_anomaly: 10.0 + mean_motion * elapsed, ..start.clone() };
+                let (position, velocity) = later.to_state().unwrap();
+                for axis in 0..3 {
+                    assert!((state.position[axis] - position[axis]).abs() < 1e-4,
+                            "e {} at {} s: {:?} vs {:?}", eccentricity, seconds, state.position, position);
+                    assert!((state.velocity[axis] - velocity[axis]).abs() < 1e-8,
+                            "e {} at {} s: {:?} vs {:?}", eccentricity, seconds, state.velocity, velocity);
+                }
+            }
+            let period = propagator.orbital_period().unwrap().num_milliseconds() as f64 / 1000.0;
+            assert!((period - 360.0 / mean_motion).abs() < 1e-2, "{} s", period);
+        }
+    }
+}
@@ -52,4 +52,76 @@ impl Default for TEME {
             sidereal: 0.0,
         }
     }
-}
\ No newline at end of file
+}
+
+/// Topocentric look angle from an observer to a satellite.
+/// azimuth and elevation in degrees, distance (slant range) in km,
+/// range_rate in km/s (positive when the satellite is moving away).
+#[derive(Debug, Copy, Clone)]
+pub struct LookAngle { 
+    pub azimuth: f64,
+    pub elevation: f64,
+    pub distance: f64,
+    pub range_rate: f64,
+}
+impl Default for LookAngle { 
+    fn default() -> LookAngle { 
+        LookAngle { 
+            azimuth: 0.0,
+            elevation: 0.0,
+            distance: 0.0,
+            range_rate: 0.0,
+        }
+    }
+}
+
+/// East, North, Up topocentric coordinates in km.
+#[derive(Debug, Copy, Clone)]
+pub struct ENU { 
+    pub east: f64,
+    pub north: f64,
+    pub up: f64,
+}
+impl Default for ENU { 
+    fn default() -> ENU { 
+        ENU { 
+            east: 0.0,
+            north: 0.0,
+            up: 0.0,
+        }
+    }
+}
+
+/// South, East, Zenith topocentric coordinates in km.
+#[derive(Debug, Copy, Clone)]
+pub struct SEZ { 
+    pub south: f64,
+    pub east: f64,
+    pub zenith: f64,
+}
+impl Default for SEZ { 
+    fn default() -> SEZ { 
+        SEZ { 
+            south: 0.0,
+            east: 0.0,
+            zenith: 0.0,
+        }
+    }
+}
+
+/// Azimuth, Elevation, Range. azimuth and elevation in degrees, range in km.
+#[derive(Debug, Copy, Clone)]
+pub struct AER { 
+    pub azimuth: f64,
+    pub elevation: f64,
+    pub range: f64,
+}
+impl Default for AER { 
+    fn default() -> AER { 
+        AER { 
+            azimuth: 0.0,
+            elevation: 0.0,
+            range: 0.0,
+        }
+    }
+}
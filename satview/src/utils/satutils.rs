@@ -0,0 +1,443 @@
+//! purpose:
+//!     satutils.rs holds the coordinate transforms and time helpers shared by
+//!     observers and satellites. All angles handed in/out of the public
+//!     functions are in degrees unless the name says otherwise, distances are in km.
+//!     Transforms:
+//!         TEME     <-> ECEF      teme_to_ecef, teme_to_ecef_velocity, ecef_to_teme
+//!         TEME     <-> Geodetic  get_geodetic, teme_to_geodetic, get_teme
+//!         Geodetic <-> ECEF      get_ecef, ecef_to_geodetic
+//!         ECEF     <-> ENU       ecef_to_enu, enu_to_ecef
+//!         ENU      <-> SEZ       enu_to_sez, sez_to_enu
+//!         ENU      <-> AER       enu_to_aer, aer_to_enu
+//!     Geodetic coordinates are on the WGS-84 ellipsoid (sgp4's own constants are WGS-72, which
+//!     only matters inside the propagator). ECEF here is the pseudo earth fixed frame, polar motion is ignored.
+//!     Sidereal time is computed from UTC, pass UT1 when sub-metre agreement matters.
+
+use chrono::NaiveDateTime;
+use sgp4::iau_epoch_to_sidereal_time;
+use sgp4::WGS84;
+use crate::coordinate_systems::{AER, ECEF, ENU, Geodetic, SEZ, TEME};
+use libm::fabs;
+
+/// WGS-84 ellipsoid, the radius is sgp4's WGS84.ae.
+const WGS84_FLATTENING: f64 = 1.0 / 298.257223563;
+const WGS84_E2: f64 = WGS84_FLATTENING * (2.0 - WGS84_FLATTENING); // Square of first eccentricity.
+const EARTH_ROTATION_RATE: f64 = 7.292115146706979e-5; // rad/s
+
+/// get_teme: position and velocity of a point fixed to the earth in the TEME frame.
+/// input: geodetic coordinates, epoch
+/// output: TEME state of the point at the epoch
+pub fn get_teme(geo_coords: &Geodetic, new_epoch: &NaiveDateTime) -> TEME {
+    ecef_to_teme(&get_ecef(geo_coords), &[0.0, 0.0, 0.0], new_epoch)
+}
+
+/// get_geodetic: geodetic coordinates of an sgp4 prediction.
+/// input: sgp4 prediction (TEME), epoch of the prediction
+/// output: geodetic coordinates (degrees, km)
+pub fn get_geodetic(propagation: &sgp4::Prediction, updated_epoch: &NaiveDateTime) -> Geodetic {
+    let teme = TEME { pos_vector: propagation.position, ..Default::default() };
+    teme_to_geodetic(&teme, updated_epoch)
+}
+
+/// get_ecef: earth centered earth fixed coordinates of a geodetic point.
+/// input: geodetic coordinates (degrees, km)
+/// output: ECEF coordinates (km)
+pub fn get_ecef(geodetic_coords: &Geodetic) -> ECEF {
+    let radians_lat = degrees_to_radians(&geodetic_coords.latitude);
+    let radians_lon = degrees_to_radians(&geodetic_coords.longitude);
+    let alt = geodetic_coords.altitude;
+    // Prime vertical radius of curvature
+    let n = WGS84.ae / (1.0 - WGS84_E2 * radians_lat.sin().powf(2.0)).sqrt();
+
+    let ecef_x: f64 = (n + alt) * radians_lat.cos() * radians_lon.cos();
+    let ecef_y: f64 = (n + alt) * radians_lat.cos() * radians_lon.sin();
+    let ecef_z = ((1.0 - WGS84_E2) * n + alt) * radians_lat.sin();
+
+    ECEF {
+        x: ecef_x,
+        y: ecef_y,
+        z: ecef_z,
+    }
+}
+
+/// ecef_to_geodetic: geodetic coordinates of an ECEF point (inverse of get_ecef).
+/// input: ECEF coordinates (km)
+/// output: geodetic coordinates (degrees, km)
+pub fn ecef_to_geodetic(ecef: &ECEF) -> Geodetic {
+    let e2 = WGS84_E2;
+    let p = (ecef.x * ecef.x + ecef.y * ecef.y).sqrt();
+    let lon = ecef.y.atan2(ecef.x);
+
+    let mut lat = ecef.z.atan2(p * (1.0 - e2));
+    let mut alt = 0.0;
+    for _ in 0..10 {
+        let sin_lat = lat.sin();
+        let n = WGS84.ae / (1.0 - e2 * sin_lat * sin_lat).sqrt();
+        // Use whichever expression stays well conditioned at this latitude.
+        alt = if lat.cos().abs() > core::f64::consts::FRAC_1_SQRT_2 {
+            p / lat.cos() - n
+        } else {
+            ecef.z / sin_lat - n * (1.0 - e2)
+        };
+        let next = ecef.z.atan2(p * (1.0 - e2 * n / (n + alt)));
+        if fabs(next - lat) < 1e-12 {
+            lat = next;
+            break;
+        }
+        lat = next;
+    }
+
+    Geodetic {
+        latitude: radians_to_degrees(&lat),
+        longitude: radians_to_degrees(&lon),
+        altitude: alt,
+    }
+}
+
+/// teme_to_ecef: rotates a TEME position into the earth fixed frame.
+/// input: TEME state, epoch (UTC)
+/// output: ECEF coordinates (km)
+pub fn teme_to_ecef(teme: &TEME, epoch: &NaiveDateTime) -> ECEF {
+    let gmst = to_sidereal(epoch);
+    let (sin_g, cos_g) = gmst.sin_cos();
+    let r = teme.pos_vector;
+
+    ECEF {
+        x: cos_g * r[0] + sin_g * r[1],
+        y: -sin_g * r[0] + cos_g * r[1],
+        z: r[2],
+    }
+}
+
+/// teme_to_ecef_velocity: earth fixed velocity of a TEME state.
+/// input: TEME state, epoch (UTC)
+/// output: ECEF velocity (km/s)
+pub fn teme_to_ecef_velocity(teme: &TEME, epoch: &NaiveDateTime) -> [f64; 3] {
+    let gmst = to_sidereal(epoch);
+    let (sin_g, cos_g) = gmst.sin_cos();
+    let v = teme.velo_vector;
+    let r = teme_to_ecef(teme, epoch);
+
+    // Rotate then remove the earth's rotation (w x r).
+    [
+        cos_g * v[0] + sin_g * v[1] + EARTH_ROTATION_RATE * r.y,
+        -sin_g * v[0] + cos_g * v[1] - EARTH_ROTATION_RATE * r.x,
+        v[2],
+    ]
+}
+
+/// ecef_to_teme: TEME state of an earth fixed position and velocity.
+/// input: ECEF coordinates (km), ECEF velocity (km/s), epoch (UTC)
+/// output: TEME state
+pub fn ecef_to_teme(ecef: &ECEF, ecef_velocity: &[f64; 3], epoch: &NaiveDateTime) -> TEME {
+    let gmst = to_sidereal(epoch);
+    let (sin_g, cos_g) = gmst.sin_cos();
+
+    // Add the earth's rotation back in before rotating.
+    let v = [
+        ecef_velocity[0] - EARTH_ROTATION_RATE * ecef.y,
+        ecef_velocity[1] + EARTH_ROTATION_RATE * ecef.x,
+        ecef_velocity[2],
+    ];
+    let pos = [cos_g * ecef.x - sin_g * ecef.y, sin_g * ecef.x + cos_g * ecef.y, ecef.z];
+    let velo = [cos_g * v[0] - sin_g * v[1], sin_g * v[0] + cos_g * v[1], v[2]];
+
+    TEME {
+        pos_vector: pos,
+        pos_magnitude: (pos[0] * pos[0] + pos[1] * pos[1] + pos[2] * pos[2]).sqrt(),
+        velo_vector: velo,
+        velo_magnitude: (velo[0] * velo[0] + velo[1] * velo[1] + velo[2] * velo[2]).sqrt(),
+        sidereal: gmst,
+    }
+}
+
+/// teme_to_geodetic: geodetic coordinates of a TEME position.
+/// input: TEME state, epoch (UTC)
+/// output: geodetic coordinates (degrees, km)
+pub fn teme_to_geodetic(teme: &TEME, epoch: &NaiveDateTime) -> Geodetic {
+    ecef_to_geodetic(&teme_to_ecef(teme, epoch))
+}
+
+/// j2000_to_teme: TEME state of a J2000 (EME2000/GCRF) position and velocity.
+/// Applies IAU-76 precession and the leading terms of the IAU-80 nutation (about 0.5 arcsec),
+/// then the equation of the equinoxes. The slow rotation of the frames themselves is left out of the velocity.
+/// input: J2000 position (km), J2000 velocity (km/s), epoch (UTC)
+/// output: TEME position (km) and velocity (km/s)
+pub fn j2000_to_teme(position: &[f64; 3], velocity: &[f64; 3], epoch: &NaiveDateTime) -> ([f64; 3], [f64; 3]) {
+    let arcsec = std::f64::consts::PI / (180.0 * 3600.0);
+    let t = sgp4::julian_years_since_j2000(epoch) / 100.0;
+
+    let zeta = (2306.2181 * t + 0.30188 * t * t + 0.017998 * t * t * t) * arcsec;
+    let theta = (2004.3109 * t - 0.42665 * t * t - 0.041833 * t * t * t) * arcsec;
+    let z = (2306.2181 * t + 1.09468 * t * t + 0.018203 * t * t * t) * arcsec;
+
+    let node = (125.04452 - 1934.136261 * t).to_radians();
+    let sun = (280.4665 + 36000.7698 * t).to_radians();
+    let moon = (218.3165 + 481267.8813 * t).to_radians();
+    let nutation_longitude = (-17.20 * node.sin() - 1.32 * (2.0 * sun).sin() - 0.23 * (2.0 * moon).sin()
+        + 0.21 * (2.0 * node).sin()) * arcsec;
+    let nutation_obliquity = (9.20 * node.cos() + 0.57 * (2.0 * sun).cos() + 0.10 * (2.0 * moon).cos()
+        - 0.09 * (2.0 * node).cos()) * arcsec;
+    let mean_obliquity = (84381.448 - 46.8150 * t) * arcsec;
+    let obliquity = mean_obliquity + nutation_obliquity;
+
+    let rotate = |vector: &[f64; 3]| {
+        let mean_of_date = rotate_z(&rotate_y(&rotate_z(vector, -zeta), theta), -z);
+        let true_of_date = rotate_x(&rotate_z(&rotate_x(&mean_of_date, mean_obliquity), -nutation_longitude), -obliquity);
+        rotate_z(&true_of_date, nutation_longitude * obliquity.cos())
+    };
+    (rotate(position), rotate(velocity))
+}
+
+/// rotate_x, rotate_y, rotate_z: a vector in axes rotated by an angle (radians) about x, y or z.
+fn rotate_x(v: &[f64; 3], angle: f64) -> [f64; 3] {
+    let (s, c) = angle.sin_cos();
+    [v[0], c * v[1] + s * v[2], -s * v[1] + c * v[2]]
+}
+
+fn rotate_y(v: &[f64; 3], angle: f64) -> [f64; 3] {
+    let (s, c) = angle.sin_cos();
+    [c * v[0] - s * v[2], v[1], s * v[0] + c * v[2]]
+}
+
+fn rotate_z(v: &[f64; 3], angle: f64) -> [f64; 3] {
+    let (s, c) = angle.sin_cos();
+    [c * v[0] + s * v[1], -s * v[0] + c * v[1], v[2]]
+}
+
+/// ecef_to_enu: position of an ECEF target relative to an observer, in its local frame.
+/// input: target ECEF coordinates (km), observer geodetic coordinates
+/// output: East, North, Up (km)
+pub fn ecef_to_enu(target: &ECEF, observer: &Geodetic) -> ENU {
+    let origin = get_ecef(observer);
+    let (sin_lat, cos_lat) = degrees_to_radians(&observer.latitude).sin_cos();
+    let (sin_lon, cos_lon) = degrees_to_radians(&observer.longitude).sin_cos();
+    let dx = target.x - origin.x;
+    let dy = target.y - origin.y;
+    let dz = target.z - origin.z;
+
+    ENU {
+        east: -sin_lon * dx + cos_lon * dy,
+        north: -sin_lat * cos_lon * dx - sin_lat * sin_lon * dy + cos_lat * dz,
+        up: cos_lat * cos_lon * dx + cos_lat * sin_lon * dy + sin_lat * dz,
+    }
+}
+
+/// enu_to_ecef: ECEF position of a point given in an observer's local frame.
+/// input: East, North, Up (km), observer geodetic coordinates
+/// output: ECEF coordinates (km)
+pub fn enu_to_ecef(enu: &ENU, observer: &Geodetic) -> ECEF {
+    let origin = get_ecef(observer);
+    let (sin_lat, cos_lat) = degrees_to_radians(&observer.latitude).sin_cos();
+    let (sin_lon, cos_lon) = degrees_to_radians(&observer.longitude).sin_cos();
+
+    ECEF {
+        x: origin.x - sin_lon * enu.east - sin_lat * cos_lon * enu.north + cos_lat * cos_lon * enu.up,
+        y: origin.y + cos_lon * enu.east - sin_lat * sin_lon * enu.north + cos_lat * sin_lon * enu.up,
+        z: origin.z + cos_lat * enu.north + sin_lat * enu.up,
+    }
+}
+
+pub fn enu_to_sez(enu: &ENU) -> SEZ {
+    SEZ {
+        south: -enu.north,
+        east: enu.east,
+        zenith: enu.up,
+    }
+}
+
+pub fn sez_to_enu(sez: &SEZ) -> ENU {
+    ENU {
+        east: sez.east,
+        north: -sez.south,
+        up: sez.zenith,
+    }
+}
+
+/// enu_to_aer: azimuth (clockwise from north), elevation and range of a local vector.
+/// input: East, North, Up (km)
+/// output: AER (degrees, km)
+pub fn enu_to_aer(enu: &ENU) -> AER {
+    let range = (enu.east * enu.east + enu.north * enu.north + enu.up * enu.up).sqrt();
+    let mut azimuth = enu.east.atan2(enu.north);
+    if azimuth < 0.0 {
+        azimuth += 2.0 * core::f64::consts::PI;
+    }
+    let elevation = if range > 0.0 { (enu.up / range).asin() } else { 0.0 };
+
+    AER {
+        azimuth: radians_to_degrees(&azimuth),
+        elevation: radians_to_degrees(&elevation),
+        range,
+    }
+}
+
+/// aer_to_enu: local vector of an azimuth, elevation and range.
+/// input: AER (degrees, km)
+/// output: East, North, Up (km)
+pub fn aer_to_enu(aer: &AER) -> ENU {
+    let (sin_az, cos_az) = degrees_to_radians(&aer.azimuth).sin_cos();
+    let (sin_el, cos_el) = degrees_to_radians(&aer.elevation).sin_cos();
+
+    ENU {
+        east: aer.range * cos_el * sin_az,
+        north: aer.range * cos_el * cos_az,
+        up: aer.range * sin_el,
+    }
+}
+
+fn float_mod(x: f64, y: f64) -> f64 {
+    if y == 0.0 {
+        return x;
+    }
+    x - y * (x / y).floor()
+}
+
+pub fn radians_to_degrees(radians: &f64) -> f64 {
+    radians * 180.0 / core::f64::consts::PI
+}
+
+pub fn degrees_to_radians(degrees: &f64) -> f64 {
+    degrees * core::f64::consts::PI / 180.0
+}
+
+fn wrap_two_pi(val: f64) -> f64 {
+    float_mod(val, core::f64::consts::PI * 2.0)
+}
+
+pub fn to_local_sidereal_time(longitude: f64, sidereal_time: &f64) -> f64 {
+    wrap_two_pi(sidereal_time + longitude)
+}
+
+pub fn to_sidereal(date_time: &NaiveDateTime) -> f64 {
+    iau_epoch_to_sidereal_time(sgp4::julian_years_since_j2000(date_time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= tolerance,
+                "expected {} got {} (tolerance {})", expected, actual, tolerance);
+    }
+
+    /// Vallado, Fundamentals of Astrodynamics and Applications, Example 3-15 (UT1 epoch).
+    fn vallado_teme() -> (TEME, NaiveDateTime) {
+        let ut1 = NaiveDate::from_ymd_opt(2004, 4, 6).unwrap()
+            .and_hms_micro_opt(7, 51, 27, 946047).unwrap();
+        let teme = TEME {
+            pos_vector: [5094.18016210, 6127.64465950, 6380.34453270],
+            velo_vector: [-4.746131487, 0.785818041, 5.531931288],
+            ..Default::default()
+        };
+        (teme, ut1)
+    }
+
+    #[test]
+    fn teme_to_ecef_matches_vallado_pef() {
+        let (teme, ut1) = vallado_teme();
+        let ecef = teme_to_ecef(&teme, &ut1);
+        assert_close(ecef.x, -1033.47503130, 1e-3);
+        assert_close(ecef.y, 7901.30558560, 1e-3);
+        assert_close(ecef.z, 6380.34453270, 1e-6);
+
+        let velocity = teme_to_ecef_velocity(&teme, &ut1);
+        assert_close(velocity[0], -3.225636520, 1e-4);
+        assert_close(velocity[1], -2.872451450, 1e-4);
+        assert_close(velocity[2], 5.531924446, 1e-4);
+    }
+
+    #[test]
+    fn ecef_to_teme_round_trip() {
+        let (teme, ut1) = vallado_teme();
+        let ecef = teme_to_ecef(&teme, &ut1);
+        let back = ecef_to_teme(&ecef, &teme_to_ecef_velocity(&teme, &ut1), &ut1);
+        for i in 0..3 {
+            assert_close(back.pos_vector[i], teme.pos_vector[i], 1e-8);
+            assert_close(back.velo_vector[i], teme.velo_vector[i], 1e-11);
+        }
+    }
+
+    #[test]
+    fn j2000_to_teme_matches_vallado() {
+        let (teme, ut1) = vallado_teme();
+        let (position, velocity) = j2000_to_teme(&[5102.508958, 6123.011401, 6378.136928],
+                                                 &[-4.743220157, 0.790536497, 5.533755727], &ut1);
+        for i in 0..3 {
+            assert_close(position[i], teme.pos_vector[i], 0.05);
+            assert_close(velocity[i], teme.velo_vector[i], 5e-5);
+        }
+    }
+
+    /// Vallado Example 3-3.
+    #[test]
+    fn ecef_to_geodetic_matches_vallado() {
+        let ecef = ECEF { x: 6524.834, y: 6862.875, z: 6448.296 };
+        let geo = ecef_to_geodetic(&ecef);
+        assert_close(geo.latitude, 34.352496, 1e-6);
+        assert_close(geo.longitude, 46.4464, 1e-4);
+        assert_close(geo.altitude, 5085.22, 1e-2);
+
+        let back = get_ecef(&geo);
+        assert_close(back.x, ecef.x, 1e-8);
+        assert_close(back.y, ecef.y, 1e-8);
+        assert_close(back.z, ecef.z, 1e-8);
+    }
+
+    #[test]
+    fn ecef_to_geodetic_poles() {
+        let north = ecef_to_geodetic(&get_ecef(&Geodetic { latitude: 90.0, longitude: 0.0, altitude: 1.5 }));
+        assert_close(north.latitude, 90.0, 1e-9);
+        assert_close(north.altitude, 1.5, 1e-8);
+        let south = ecef_to_geodetic(&get_ecef(&Geodetic { latitude: -89.9, longitude: -120.0, altitude: 0.0 }));
+        assert_close(south.latitude, -89.9, 1e-9);
+        assert_close(south.longitude, -120.0, 1e-9);
+        assert_close(south.altitude, 0.0, 1e-8);
+    }
+
+    #[test]
+    fn observer_teme_round_trip() {
+        let (_, ut1) = vallado_teme();
+        let site = Geodetic { latitude: 33.4484, longitude: -112.0740, altitude: 0.331 };
+        let teme = get_teme(&site, &ut1);
+        let back = teme_to_geodetic(&teme, &ut1);
+        assert_close(back.latitude, site.latitude, 1e-9);
+        assert_close(back.longitude, site.longitude, 1e-9);
+        assert_close(back.altitude, site.altitude, 1e-8);
+        // A point on the ground moves with the earth only.
+        for component in teme_to_ecef_velocity(&teme, &ut1) {
+            assert_close(component, 0.0, 1e-12);
+        }
+        let prediction = sgp4::Prediction { position: teme.pos_vector, velocity: teme.velo_vector };
+        assert_close(get_geodetic(&prediction, &ut1).latitude, site.latitude, 1e-9);
+    }
+
+    #[test]
+    fn enu_aer_sez_round_trip() {
+        let site = Geodetic { latitude: 39.007, longitude: -104.883, altitude: 2.19456 };
+        let aer = AER { azimuth: 205.6, elevation: 30.7, range: 604.68 };
+        let enu = aer_to_enu(&aer);
+        let target = enu_to_ecef(&enu, &site);
+
+        let back = enu_to_aer(&ecef_to_enu(&target, &site));
+        assert_close(back.azimuth, aer.azimuth, 1e-9);
+        assert_close(back.elevation, aer.elevation, 1e-9);
+        assert_close(back.range, aer.range, 1e-9);
+
+        let sez = enu_to_sez(&enu);
+        assert_close(sez.south, -enu.north, 0.0);
+        let enu_back = sez_to_enu(&sez);
+        assert_close(enu_back.north, enu.north, 0.0);
+    }
+
+    #[test]
+    fn enu_of_point_straight_up() {
+        let site = Geodetic { latitude: 33.4484, longitude: -112.0740, altitude: 0.0 };
+        let above = get_ecef(&Geodetic { altitude: 500.0, ..site });
+        let aer = enu_to_aer(&ecef_to_enu(&above, &site));
+        assert_close(aer.elevation, 90.0, 1e-6);
+        assert_close(aer.range, 500.0, 1e-6);
+    }
+}
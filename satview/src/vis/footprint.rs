@@ -0,0 +1,119 @@
+//! purpose:
+//!     footprint.rs computes the ground footprint of a satellite: the region of the earth
+//!     from which the satellite is seen above a given elevation. The earth is treated as
+//!     a sphere of mean radius, which is well inside a pixel on the map.
+
+use crate::coordinate_systems::Geodetic;
+use crate::render::projection::wrap_longitude;
+use crate::utils::satutils::{degrees_to_radians, radians_to_degrees};
+
+/// Mean earth radius in km.
+pub const EARTH_MEAN_RADIUS: f64 = 6371.0;
+
+/// footprint_half_angle: earth central angle from the sub-satellite point to the
+/// footprint edge.
+/// input: satellite altitude in km, minimum elevation in degrees
+/// output: central angle in degrees
+pub fn footprint_half_angle(altitude: &f64, min_elevation: &f64) -> f64 {
+    let elevation = degrees_to_radians(min_elevation);
+    let ratio = EARTH_MEAN_RADIUS / (EARTH_MEAN_RADIUS + altitude.max(0.0));
+    radians_to_degrees(&((ratio * elevation.cos()).acos() - elevation))
+}
+
+/// central_angle: great circle angle between two points, degrees.
+pub fn central_angle(from: &Geodetic, to: &Geodetic) -> f64 {
+    let lat1 = degrees_to_radians(&from.latitude);
+    let lat2 = degrees_to_radians(&to.latitude);
+    let dlon = degrees_to_radians(&(to.longitude - from.longitude));
+    let cos_angle = lat1.sin() * lat2.sin() + lat1.cos() * lat2.cos() * dlon.cos();
+    radians_to_degrees(&cos_angle.clamp(-1.0, 1.0).acos())
+}
+
+/// in_footprint: true when the ground point sees the satellite at or above min_elevation.
+/// input: sub-satellite point (with altitude), ground point, minimum elevation in degrees
+pub fn in_footprint(sub_point: &Geodetic, ground: &Geodetic, min_elevation: &f64) -> bool {
+    central_angle(sub_point, ground) <= footprint_half_angle(&sub_point.altitude, min_elevation)
+}
+
+/// footprint_contour: footprint boundary as a closed lat/lon polygon.
+/// input: sub-satellite point (with altitude), minimum elevation in degrees, number of vertices
+/// output: vertices at evenly spaced azimuths from north, the first vertex is repeated at the end
+pub fn footprint_contour(sub_point: &Geodetic, min_elevation: &f64, vertices: usize) -> Vec<Geodetic> {
+    let angle = degrees_to_radians(&footprint_half_angle(&sub_point.altitude, min_elevation));
+    let lat0 = degrees_to_radians(&sub_point.latitude);
+    let lon0 = degrees_to_radians(&sub_point.longitude);
+    let vertices = vertices.max(3);
+
+    let mut contour: Vec<Geodetic> = (0..vertices)
+        .map(|index| {
+            let azimuth = 2.0 * core::f64::consts::PI * index as f64 / vertices as f64;
+            let lat = (lat0.sin() * angle.cos() + lat0.cos() * angle.sin() * azimuth.cos()).asin();
+            let lon = lon0 + (azimuth.sin() * angle.sin() * lat0.cos())
+                .atan2(angle.cos() - lat0.sin() * lat.sin());
+            Geodetic {
+                latitude: radians_to_degrees(&lat),
+                longitude: wrap_longitude(radians_to_degrees(&lon)),
+                altitude: 0.0,
+            }
+        })
+        .collect();
+    contour.push(contour[0]);
+    contour
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUB_POINTS: [Geodetic; 3] = [
+        Geodetic { latitude: 0.0, longitude: 0.0, altitude: 700.0 },
+        Geodetic { latitude: 33.4484, longitude: -112.0740, altitude: 1200.0 },
+        // Over the antimeridian and close enough to the pole for the footprint to cover it.
+        Geodetic { latitude: 80.0, longitude: 175.0, altitude: 8062.0 },
+    ];
+
+    #[test]
+    fn half_angle_matches_the_closed_form() {
+        for altitude in [500.0, 1200.0, 8062.0, 35_786.0] {
+            let horizon = (EARTH_MEAN_RADIUS / (EARTH_MEAN_RADIUS + altitude)).acos().to_degrees();
+            assert!((footprint_half_angle(&altitude, &0.0) - horizon).abs() < 1e-9);
+            assert!(footprint_half_angle(&altitude, &90.0).abs() < 1e-9);
+            let elevation: f64 = 20.0;
+            let closed_form = (EARTH_MEAN_RADIUS / (EARTH_MEAN_RADIUS + altitude) * elevation.to_radians().cos()).acos()
+                .to_degrees() - elevation;
+            assert!((footprint_half_angle(&altitude, &elevation) - closed_form).abs() < 1e-9);
+        }
+        // Geostationary orbit sees a bit over 81 degrees of arc either way.
+        assert!((footprint_half_angle(&35_786.0, &0.0) - 81.3).abs() < 0.05);
+    }
+
+    #[test]
+    fn contour_lies_on_the_footprint_edge() {
+        for sub_point in SUB_POINTS {
+            for elevation in [0.0, 10.0, 30.0] {
+                let half_angle = footprint_half_angle(&sub_point.altitude, &elevation);
+                let contour = footprint_contour(&sub_point, &elevation, 72);
+                assert_eq!(contour.len(), 73);
+                assert_eq!((contour[0].latitude, contour[0].longitude), (contour[72].latitude, contour[72].longitude));
+                for point in &contour {
+                    assert!((-180.0..180.0).contains(&point.longitude), "{:?}", point);
+                    assert!((central_angle(&sub_point, point) - half_angle).abs() < 1e-6, "{:?}", point);
+                    assert!(in_footprint(&sub_point, point, &(elevation - 0.01)));
+                    assert!(!in_footprint(&sub_point, point, &(elevation + 0.01)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn higher_elevation_contours_nest_inside_lower_ones() {
+        for sub_point in SUB_POINTS {
+            for (low, high) in [(0.0, 10.0), (10.0, 20.0), (20.0, 30.0)] {
+                for point in footprint_contour(&sub_point, &high, 36) {
+                    assert!(in_footprint(&sub_point, &point, &low));
+                    assert!(central_angle(&sub_point, &point) < footprint_half_angle(&sub_point.altitude, &low));
+                }
+            }
+        }
+    }
+}
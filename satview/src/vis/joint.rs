@@ -0,0 +1,165 @@
+//! purpose:
+//!     joint.rs finds the intervals when a satellite is visible to several observers at
+//!     once, each above its own elevation mask. This is the bent-pipe case where a gateway
+//!     and a terminal must both see the satellite for the link to close.
+//!     For a single observer use passes::find_passes directly.
+
+use chrono::{NaiveDateTime, TimeDelta};
+use crate::obs::observer::Observer;
+use crate::sat::satellite::Satellite;
+use crate::vis::passes::{find_passes, PassConfig};
+
+/// Interval of simultaneous visibility.
+/// start_observer / end_observer are the indices of the observers whose mask
+/// opens and closes the interval.
+#[derive(Debug, Copy, Clone)]
+pub struct JointWindow {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub start_observer: usize,
+    pub end_observer: usize,
+}
+
+impl JointWindow {
+    pub fn duration(&self) -> TimeDelta {
+        self.end - self.start
+    }
+}
+
+/// find_joint_windows: intervals when every observer sees the satellite above its mask.
+/// config.min_elevation is ignored, each observer's elevation_mask is used instead.
+/// input: observers, satellite, start and end of the search range (UTC), search config
+/// output: windows in chronological order, clipped to the search range
+pub fn find_joint_windows(observers: &[Observer],
+                          satellite: &mut Satellite,
+                          start: &NaiveDateTime,
+                          end: &NaiveDateTime,
+                          config: &PassConfig) -> anyhow::Result<Vec<JointWindow>> {
+    if observers.is_empty() {
+        anyhow::bail!("joint visibility needs at least one observer");
+    }
+
+    let mut joint: Option<Vec<JointWindow>> = None;
+    for (index, observer) in observers.iter().enumerate() {
+        let observer_config = PassConfig {
+            min_elevation: observer.elevation_mask,
+            ..*config
+        };
+        let windows: Vec<JointWindow> = find_passes(observer, satellite, start, end, &observer_config)?
+            .iter()
+            .map(|pass| JointWindow {
+                start: pass.aos,
+                end: pass.los,
+                start_observer: index,
+                end_observer: index,
+            })
+            .collect();
+
+        joint = Some(match joint {
+            None => windows,
+            Some(previous) => intersect(&previous, &windows),
+        });
+    }
+
+    Ok(joint.unwrap_or_default())
+}
+
+/// intersect: overlap of two sorted, non-overlapping interval lists.
+fn intersect(first: &[JointWindow], second: &[JointWindow]) -> Vec<JointWindow> {
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < first.len() && j < second.len() {
+        let a = &first[i];
+        let b = &second[j];
+        let (start, start_observer) = if a.start >= b.start { (a.start, a.start_observer) } else { (b.start, b.start_observer) };
+        let (end, end_observer) = if a.end <= b.end { (a.end, a.end_observer) } else { (b.end, b.end_observer) };
+
+        if start < end {
+            result.push(JointWindow {
+                start,
+                end,
+                start_observer,
+                end_observer,
+            });
+        }
+
+        // Advance whichever interval finishes first.
+        if a.end <= b.end {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::sat::tle::parse_tles;
+
+    fn at(minutes: i64) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2012, 10, 15).unwrap().and_hms_opt(0, 0, 0).unwrap() + TimeDelta::minutes(minutes)
+    }
+
+    fn window(start: i64, end: i64, observer: usize) -> JointWindow {
+        JointWindow { start: at(start), end: at(end), start_observer: observer, end_observer: observer }
+    }
+
+    fn bounds(windows: &[JointWindow]) -> Vec<(NaiveDateTime, NaiveDateTime, usize, usize)> {
+        windows.iter().map(|window| (window.start, window.end, window.start_observer, window.end_observer)).collect()
+    }
+
+    #[test]
+    fn intersect_keeps_the_overlaps() {
+        let first = [window(0, 10, 0), window(20, 40, 0), window(50, 60, 0), window(70, 80, 0)];
+        let second = [
+            window(5, 15, 1),  // overlaps the end of [0, 10]
+            window(25, 30, 1), // nested in [20, 40]
+            window(35, 50, 1), // overlaps the end of [20, 40], touches [50, 60]
+            window(62, 68, 1), // disjoint
+        ];
+        assert_eq!(bounds(&intersect(&first, &second)),
+                   [(at(5), at(10), 1, 0), (at(25), at(30), 1, 1), (at(35), at(40), 1, 0)]);
+        assert_eq!(bounds(&intersect(&second, &first)),
+                   [(at(5), at(10), 1, 0), (at(25), at(30), 1, 1), (at(35), at(40), 1, 0)]);
+        assert!(intersect(&first, &[]).is_empty());
+    }
+
+    #[test]
+    fn each_observer_keeps_its_own_mask() {
+        let start = at(0);
+        let end = start + TimeDelta::days(1);
+        let mut gateway = Observer::from_coordinates([33.4484, -112.0740, 0.331]);
+        gateway.elevation_mask = 5.0;
+        // Denver, 850 km away, with a higher mask.
+        let mut terminal = Observer::from_coordinates([39.7392, -104.9903, 1.609]);
+        terminal.elevation_mask = 10.0;
+        let observers = [gateway, terminal];
+        let mut satellite = Satellite::new(parse_tles(include_str!("../../common/tle2.txt")).elements.remove(0));
+
+        let windows = find_joint_windows(&observers, &mut satellite, &start, &end, &PassConfig::default()).unwrap();
+        // The two passes of the day over both, one heading north and one south: the site the
+        // satellite reaches first opens the window, the one it leaves first closes it.
+        assert_eq!(windows.iter().map(|window| (window.start_observer, window.end_observer)).collect::<Vec<_>>(),
+                   [(0, 1), (1, 0)]);
+        let mut elevations = |time: &NaiveDateTime| {
+            satellite.propagate(time).unwrap();
+            observers.map(|mut observer| observer.look_angle_to(&satellite).elevation)
+        };
+        for window in &windows {
+            assert!(window.start > start && window.end < end);
+            // The observer named at each end sits on its own mask there, the other one is above its own.
+            for (time, index) in [(window.start, window.start_observer), (window.end, window.end_observer)] {
+                let elevation = elevations(&time);
+                assert!((elevation[index] - observers[index].elevation_mask).abs() < 1e-2,
+                        "observer {} at {} deg at {}", index, elevation[index], time);
+                let other = 1 - index;
+                assert!(elevation[other] >= observers[other].elevation_mask - 1e-2,
+                        "observer {} at {} deg at {}", other, elevation[other], time);
+            }
+        }
+    }
+}
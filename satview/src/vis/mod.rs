@@ -0,0 +1,3 @@
+pub mod passes;
+pub mod joint;
+pub mod footprint;
\ No newline at end of file
@@ -0,0 +1,339 @@
+//! purpose:
+//!     passes.rs predicts visibility windows (passes) of a satellite for an observer.
+//!     A pass starts at acquisition of signal (AOS), when the satellite climbs above the
+//!     observer's minimum elevation, and ends at loss of signal (LOS) when it drops below it.
+
+use chrono::{NaiveDateTime, TimeDelta};
+use crate::coordinate_systems::LookAngle;
+use crate::obs::observer::Observer;
+use crate::sat::satellite::Satellite;
+
+/// Minimum elevation used by the original propagation loop, in degrees.
+pub const DEFAULT_MIN_ELEVATION: f64 = 15.0;
+
+/// A single visibility window. Angles in degrees.
+/// Windows that are already open at the start of the search range (or still open
+/// at its end) are clipped to the range.
+/// tle_age is the time from the TLE epoch at AOS and at LOS, None without a TLE.
+#[derive(Debug, Copy, Clone)]
+pub struct PassWindow {
+    pub aos: NaiveDateTime,
+    pub aos_azimuth: f64,
+    pub los: NaiveDateTime,
+    pub los_azimuth: f64,
+    pub max_elevation_time: NaiveDateTime,
+    pub max_elevation: f64,
+    pub max_elevation_azimuth: f64,
+    pub tle_age: Option<(TimeDelta, TimeDelta)>,
+}
+
+impl PassWindow {
+    pub fn duration(&self) -> TimeDelta {
+        self.los - self.aos
+    }
+}
+
+/// Search settings for find_passes.
+/// min_elevation in degrees, an observer's elevation_mask above it takes precedence.
+/// step is the coarse scan interval, it must be shorter than the shortest pass of interest.
+/// tolerance is the accuracy of the refined AOS, LOS and max elevation times.
+#[derive(Debug, Copy, Clone)]
+pub struct PassConfig {
+    pub min_elevation: f64,
+    pub step: TimeDelta,
+    pub tolerance: TimeDelta,
+}
+
+impl Default for PassConfig {
+    fn default() -> PassConfig {
+        PassConfig {
+            min_elevation: DEFAULT_MIN_ELEVATION,
+            step: TimeDelta::seconds(60),
+            tolerance: TimeDelta::milliseconds(1),
+        }
+    }
+}
+
+/// find_passes: visibility windows of a satellite for an observer.
+/// The range is scanned every config.step, each elevation threshold crossing found by the
+/// scan is then refined by bisection and the culmination by golden section search.
+/// The threshold is the higher of config.min_elevation and the observer's elevation_mask,
+/// plus the satellite's staleness margin.
+/// input: observer, satellite, start and end of the search range (UTC), search config
+/// output: windows in chronological order
+pub fn find_passes(observer: &Observer,
+                   satellite: &mut Satellite,
+                   start: &NaiveDateTime,
+                   end: &NaiveDateTime,
+                   config: &PassConfig) -> anyhow::Result<Vec<PassWindow>> {
+    if end <= start {
+        anyhow::bail!("pass search range end {} is not after start {}", end, start);
+    }
+    if config.step <= TimeDelta::zero() {
+        anyhow::bail!("pass search step must be positive");
+    }
+    if config.tolerance <= TimeDelta::zero() {
+        anyhow::bail!("pass search tolerance must be positive");
+    }
+
+    // A stale TLE can raise the threshold, see StalenessAction::WidenMargin.
+    let min_elevation = config.min_elevation.max(observer.elevation_mask) + satellite.elevation_margin(start, end);
+    let mut observer = *observer;
+    let epoch = *start;
+    // All searching is done in seconds since the start of the range.
+    let mut look_angle_at = |offset: f64| -> anyhow::Result<LookAngle> {
+        satellite.propagate(&to_datetime(&epoch, offset))?;
+        Ok(observer.look_angle_to(satellite))
+    };
+
+    let span = to_seconds(&(*end - *start));
+    let step = to_seconds(&config.step);
+    let tolerance = to_seconds(&config.tolerance);
+
+    let mut windows: Vec<PassWindow> = Vec::new();
+    // (aos offset, aos look angle, offset of highest sample, highest sample elevation)
+    let mut current: Option<(f64, LookAngle, f64, f64)> = None;
+    let mut prev = (0.0, look_angle_at(0.0)?);
+    if prev.1.elevation >= min_elevation {
+        current = Some((0.0, prev.1, 0.0, prev.1.elevation));
+    }
+
+    while prev.0 < span {
+        let offset = (prev.0 + step).min(span);
+        let look = look_angle_at(offset)?;
+        let visible = look.elevation >= min_elevation;
+
+        match current.as_mut() {
+            None if visible => {
+                let aos = bisect_crossing(&mut look_angle_at, min_elevation, prev.0, offset, tolerance)?;
+                let aos_look = look_angle_at(aos)?;
+                current = Some((aos, aos_look, offset, look.elevation));
+            }
+            Some((_, _, best, best_elevation)) if visible && look.elevation > *best_elevation => {
+                *best = offset;
+                *best_elevation = look.elevation;
+            }
+            Some(_) if visible => {}
+            Some((aos, aos_look, best, _)) => {
+                let los = bisect_crossing(&mut look_angle_at, min_elevation, prev.0, offset, tolerance)?;
+                let window = close_window(&mut look_angle_at, &epoch, (*aos, *aos_look), los, *best, step, tolerance)?;
+                windows.push(window);
+                current = None;
+            }
+            None => {}
+        }
+
+        prev = (offset, look);
+    }
+
+    if let Some((aos, aos_look, best, _)) = current {
+        let window = close_window(&mut look_angle_at, &epoch, (aos, aos_look), span, best, step, tolerance)?;
+        windows.push(window);
+    }
+
+    // close_window only sees look angles, the ages come from the satellite.
+    for window in windows.iter_mut() {
+        window.tle_age = satellite.tle_age_at(&window.aos).zip(satellite.tle_age_at(&window.los));
+    }
+    Ok(windows)
+}
+
+/// close_window: refines the culmination of a pass and builds the window.
+fn close_window<F>(look_angle_at: &mut F,
+                   epoch: &NaiveDateTime,
+                   aos: (f64, LookAngle),
+                   los: f64,
+                   best: f64,
+                   step: f64,
+                   tolerance: f64) -> anyhow::Result<PassWindow>
+where
+    F: FnMut(f64) -> anyhow::Result<LookAngle>,
+{
+    let los_look = look_angle_at(los)?;
+    let lower = (best - step).max(aos.0);
+    let upper = (best + step).min(los);
+    let max_offset = golden_section_max(look_angle_at, lower, upper, tolerance)?;
+    let max_look = look_angle_at(max_offset)?;
+
+    Ok(PassWindow {
+        aos: to_datetime(epoch, aos.0),
+        aos_azimuth: aos.1.azimuth,
+        los: to_datetime(epoch, los),
+        los_azimuth: los_look.azimuth,
+        max_elevation_time: to_datetime(epoch, max_offset),
+        max_elevation: max_look.elevation,
+        max_elevation_azimuth: max_look.azimuth,
+        tle_age: None,
+    })
+}
+
+/// bisect_crossing: time at which the elevation crosses the threshold within [lower, upper].
+/// The elevation must be on opposite sides of the threshold at lower and upper.
+/// output: the crossing offset on the visible side of the threshold.
+fn bisect_crossing<F>(look_angle_at: &mut F,
+                      threshold: f64,
+                      lower: f64,
+                      upper: f64,
+                      tolerance: f64) -> anyhow::Result<f64>
+where
+    F: FnMut(f64) -> anyhow::Result<LookAngle>,
+{
+    let rising = look_angle_at(lower)?.elevation < threshold;
+    let (mut below, mut above) = if rising { (lower, upper) } else { (upper, lower) };
+
+    while (above - below).abs() > tolerance {
+        let mid = 0.5 * (below + above);
+        if look_angle_at(mid)?.elevation >= threshold {
+            above = mid;
+        } else {
+            below = mid;
+        }
+    }
+    Ok(above)
+}
+
+/// golden_section_max: offset of maximum elevation within [lower, upper].
+/// The elevation is assumed unimodal within the interval.
+fn golden_section_max<F>(look_angle_at: &mut F,
+                         mut lower: f64,
+                         mut upper: f64,
+                         tolerance: f64) -> anyhow::Result<f64>
+where
+    F: FnMut(f64) -> anyhow::Result<LookAngle>,
+{
+    let inv_phi = (5.0_f64.sqrt() - 1.0) / 2.0;
+    let mut x1 = upper - inv_phi * (upper - lower);
+    let mut x2 = lower + inv_phi * (upper - lower);
+    let mut f1 = look_angle_at(x1)?.elevation;
+    let mut f2 = look_angle_at(x2)?.elevation;
+
+    while upper - lower > tolerance {
+        if f1 < f2 {
+            lower = x1;
+            x1 = x2;
+            f1 = f2;
+            x2 = lower + inv_phi * (upper - lower);
+            f2 = look_angle_at(x2)?.elevation;
+        } else {
+            upper = x2;
+            x2 = x1;
+            f2 = f1;
+            x1 = upper - inv_phi * (upper - lower);
+            f1 = look_angle_at(x1)?.elevation;
+        }
+    }
+    Ok(0.5 * (lower + upper))
+}
+
+fn to_seconds(delta: &TimeDelta) -> f64 {
+    delta.num_seconds() as f64 + delta.subsec_nanos() as f64 * 1e-9
+}
+
+fn to_datetime(epoch: &NaiveDateTime, offset: f64) -> NaiveDateTime {
+    *epoch + TimeDelta::nanoseconds((offset * 1e9).round() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::sat::tle::parse_tles;
+
+    fn uk_dmc_2() -> Satellite {
+        Satellite::new(parse_tles(include_str!("../../common/tle2.txt")).elements.remove(0))
+    }
+
+    fn day() -> (NaiveDateTime, NaiveDateTime) {
+        let start = NaiveDate::from_ymd_opt(2012, 10, 15).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        (start, start + TimeDelta::days(1))
+    }
+
+    fn elevation(observer: &Observer, satellite: &mut Satellite, time: &NaiveDateTime) -> f64 {
+        let mut observer = *observer;
+        satellite.propagate(time).unwrap();
+        observer.look_angle_to(satellite).elevation
+    }
+
+    /// threshold: the elevation find_passes searches with.
+    fn threshold(observer: &Observer, satellite: &Satellite, config: &PassConfig) -> f64 {
+        let (start, end) = day();
+        config.min_elevation.max(observer.elevation_mask) + satellite.elevation_margin(&start, &end)
+    }
+
+    #[test]
+    fn windows_bracket_the_time_above_the_mask() {
+        let (start, end) = day();
+        let observer = Observer::from_coordinates([33.4484, -112.0740, 0.331]);
+        let mut satellite = uk_dmc_2();
+        let config = PassConfig::default();
+        let windows = find_passes(&observer, &mut satellite, &start, &end, &config).unwrap();
+        assert!(windows.len() >= 2, "{} windows", windows.len());
+
+        for (index, window) in windows.iter().enumerate() {
+            assert!(window.aos < window.max_elevation_time && window.max_elevation_time < window.los);
+            assert!(window.max_elevation >= threshold(&observer, &satellite, &config));
+            assert_eq!(window.tle_age, Some((satellite.tle_age_at(&window.aos).unwrap(), satellite.tle_age_at(&window.los).unwrap())));
+            if index > 0 {
+                assert!(windows[index - 1].los < window.aos);
+            }
+            // The culmination is the highest point of a one second walk through the pass.
+            let mut time = window.aos;
+            while time <= window.los {
+                assert!(elevation(&observer, &mut satellite, &time) <= window.max_elevation + 1e-6,
+                        "{} is above the culmination of the pass at {}", time, window.max_elevation_time);
+                time += TimeDelta::seconds(1);
+            }
+        }
+    }
+
+    #[test]
+    fn crossings_are_refined_to_a_millisecond() {
+        let (start, end) = day();
+        let observer = Observer::from_coordinates([33.4484, -112.0740, 0.331]);
+        let mut satellite = uk_dmc_2();
+        // Above the observer's 15 degree mask, so the config sets the threshold.
+        let config = PassConfig { min_elevation: 20.0, ..Default::default() };
+        let mask = threshold(&observer, &satellite, &config);
+        assert_eq!(mask, 20.0);
+        let windows = find_passes(&observer, &mut satellite, &start, &end, &config).unwrap();
+        assert!(windows.iter().all(|window| window.max_elevation >= mask));
+        let millisecond = TimeDelta::milliseconds(1);
+
+        for window in &windows {
+            assert!(elevation(&observer, &mut satellite, &window.aos) >= mask);
+            assert!(elevation(&observer, &mut satellite, &(window.aos - millisecond)) < mask, "AOS {}", window.aos);
+            assert!(elevation(&observer, &mut satellite, &window.los) >= mask);
+            assert!(elevation(&observer, &mut satellite, &(window.los + millisecond)) < mask, "LOS {}", window.los);
+        }
+
+        // A search that starts and ends inside passes clips them to the range.
+        let inside = windows[0].aos + TimeDelta::seconds(30);
+        let until = windows[1].los - TimeDelta::seconds(30);
+        let clipped = find_passes(&observer, &mut satellite, &inside, &until, &config).unwrap();
+        assert_eq!(clipped.len(), 2);
+        assert_eq!(clipped[0].aos, inside);
+        assert!((clipped[0].los - windows[0].los).abs() <= millisecond);
+        assert!((clipped[1].aos - windows[1].aos).abs() <= millisecond);
+        assert_eq!(clipped[1].los, until);
+    }
+
+    #[test]
+    fn observer_mask_raises_the_threshold() {
+        let (start, end) = day();
+        let mut masked = Observer::from_coordinates([33.4484, -112.0740, 0.331]);
+        masked.elevation_mask = 20.0;
+        let open = Observer { elevation_mask: 0.0, ..masked };
+        let low = PassConfig { min_elevation: 0.0, ..Default::default() };
+        let high = PassConfig { min_elevation: 20.0, ..Default::default() };
+
+        let by_mask = find_passes(&masked, &mut uk_dmc_2(), &start, &end, &low).unwrap();
+        let by_config = find_passes(&open, &mut uk_dmc_2(), &start, &end, &high).unwrap();
+        assert!(!by_mask.is_empty());
+        assert_eq!(by_mask.len(), by_config.len());
+        for (a, b) in by_mask.iter().zip(&by_config) {
+            assert_eq!((a.aos, a.los), (b.aos, b.los));
+        }
+        // The lower of the two never wins.
+        assert!(find_passes(&open, &mut uk_dmc_2(), &start, &end, &low).unwrap().len() > by_mask.len());
+    }
+}